@@ -0,0 +1,124 @@
+//! Compares the closest-hit search of a linear `HittableList` with `BvhNode` and `Bvh`.
+//!
+//! Run with `cargo bench --bench bvh`.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use raytracer::common::{random_double, random_double_range, seed_random};
+use raytracer::*;
+
+/// Spheres are scattered in a cube whose side grows with their number,
+/// so the density of the scene stays the same.
+fn random_spheres(count: usize) -> HittableList {
+    let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let half_side = (count as f64).cbrt() * 2.0;
+
+    let mut list = HittableList::new();
+    for _ in 0..count {
+        let center = Vec3::random_range(-half_side, half_side);
+        let radius = random_double_range(0.1, 0.5);
+        list.add(Box::new(Sphere::new(center, radius, material.clone())));
+    }
+    list
+}
+
+/// Rays starting outside the spheres and aimed at random points inside them.
+fn random_rays(count: usize, half_side: f64) -> Vec<Ray> {
+    (0..count)
+        .map(|_| {
+            let origin = vec3::random_unit_vector() * half_side * 3.0;
+            let target = Vec3::random_range(-half_side, half_side) * random_double();
+            Ray::new(origin, target - origin)
+        })
+        .collect()
+}
+
+/// Traces every ray and returns the elapsed time and the distances of the hits.
+fn trace(world: &dyn Hittable, rays: &[Ray]) -> (Duration, Vec<Option<f64>>) {
+    let start = Instant::now();
+    let hits = rays
+        .iter()
+        .map(|ray| world.hit(ray, 0.001, f64::INFINITY).map(|rec| rec.t))
+        .collect();
+    (start.elapsed(), hits)
+}
+
+fn report(name: &str, elapsed: Duration, rays: usize) {
+    let per_ray = elapsed.as_secs_f64() / rays as f64;
+    println!(
+        "  {:<12} {:>10.3} ms  {:>12.0} rays/s",
+        name,
+        elapsed.as_secs_f64() * 1e3,
+        1.0 / per_ray
+    );
+}
+
+fn bench(spheres: usize, linear_rays: usize, bvh_rays: usize) {
+    seed_random(0x5eed);
+    let half_side = (spheres as f64).cbrt() * 2.0;
+    println!("{} spheres", spheres);
+
+    let start = Instant::now();
+    let bvh = Bvh::new(random_spheres(spheres));
+    println!("  Bvh built in {:.3} ms", start.elapsed().as_secs_f64() * 1e3);
+
+    seed_random(0x5eed);
+    let start = Instant::now();
+    let bvh_node = BvhNode::new(random_spheres(spheres));
+    println!("  BvhNode built in {:.3} ms", start.elapsed().as_secs_f64() * 1e3);
+
+    seed_random(0x5eed);
+    let linear = random_spheres(spheres);
+
+    let rays = random_rays(bvh_rays, half_side);
+
+    // The linear list is too slow for as many rays, it only traces the first ones
+    let (elapsed, expected) = trace(&linear, &rays[..linear_rays]);
+    report("linear", elapsed, linear_rays);
+
+    let (elapsed, hits) = trace(&bvh_node, &rays);
+    report("BvhNode", elapsed, bvh_rays);
+    assert_eq!(hits[..linear_rays], expected[..], "BvhNode and linear search disagree");
+
+    let (elapsed, hits) = trace(&bvh, &rays);
+    report("Bvh (SAH)", elapsed, bvh_rays);
+    assert_eq!(hits[..linear_rays], expected[..], "Bvh and linear search disagree");
+}
+
+/// Spheres whose centers and radii grow geometrically along x, which gives a hierarchy
+/// much deeper than a balanced one: checks that traversing it still works. The
+/// coordinates stay far enough from the largest floats for the hits to be exact.
+fn degenerate(spheres: usize, rays: usize) {
+    println!("{} spheres growing along x", spheres);
+    let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+    let mut list = HittableList::new();
+    let mut linear = HittableList::new();
+    for i in 0..spheres {
+        let x = 1.01f64.powi(i as i32);
+        list.add(Box::new(Sphere::new(Point3::new(x, 0.0, 0.0), 0.01 * x, material.clone())));
+        linear.add(Box::new(Sphere::new(Point3::new(x, 0.0, 0.0), 0.01 * x, material.clone())));
+    }
+    let bvh = Bvh::new(list);
+
+    // Straight down onto random spheres, some of them between two spheres
+    seed_random(0x5eed);
+    let rays: Vec<Ray> = (0..rays)
+        .map(|_| {
+            let x = 1.01f64.powf(random_double() * spheres as f64);
+            Ray::new(Point3::new(x, x, 0.0), Vec3::new(0.0, -1.0, 0.0))
+        })
+        .collect();
+
+    let (elapsed, expected) = trace(&linear, &rays);
+    report("linear", elapsed, rays.len());
+    let (elapsed, hits) = trace(&bvh, &rays);
+    report("Bvh (SAH)", elapsed, rays.len());
+    assert_eq!(hits, expected, "Bvh and linear search disagree");
+}
+
+fn main() {
+    bench(10_000, 10_000, 200_000);
+    bench(1_000_000, 200, 200_000);
+    degenerate(20_000, 1_000);
+}
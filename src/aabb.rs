@@ -0,0 +1,174 @@
+use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
+
+/// Minimum thickness of a box, so flat objects like quads still have a volume.
+const MIN_THICKNESS: f64 = 1e-4;
+
+/// An axis-aligned bounding box, the smallest box containing an object.
+///
+/// Testing a ray against a box is much cheaper than against most objects,
+/// so boxes are used to skip whole groups of objects a ray cannot hit.
+///
+/// # Example
+///
+/// ```
+/// use raytracer::{Aabb, Point3, Ray, Vec3};
+///
+/// let bbox = Aabb::new(Point3::new(-1.0, -1.0, -1.0), Point3::new(1.0, 1.0, 1.0));
+/// let ray = Ray::new(Point3::new(0.0, 0.0, -5.0), Vec3::new(0.0, 0.0, 1.0));
+/// assert!(bbox.hit(&ray, 0.0, f64::INFINITY));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct Aabb {
+    min: Point3,
+    max: Point3,
+}
+
+impl Default for Aabb {
+    fn default() -> Self {
+        Aabb::empty()
+    }
+}
+
+impl Aabb {
+    /// Creates the box having `a` and `b` as opposite corners, in any order.
+    pub fn new(a: Point3, b: Point3) -> Aabb {
+        Aabb {
+            min: Point3::new(a.x().min(b.x()), a.y().min(b.y()), a.z().min(b.z())),
+            max: Point3::new(a.x().max(b.x()), a.y().max(b.y()), a.z().max(b.z())),
+        }
+        .padded()
+    }
+
+    /// Creates the box from its two corners without padding it, so it may be flat.
+    pub(crate) fn from_corners(min: Point3, max: Point3) -> Aabb {
+        Aabb { min, max }
+    }
+
+    /// A box containing nothing. Merging it with another box gives the other box.
+    pub fn empty() -> Aabb {
+        Aabb {
+            min: Point3::new(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Point3::new(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    /// Creates the smallest box containing every point of `points`.
+    pub fn from_points(points: &[Point3]) -> Aabb {
+        points
+            .iter()
+            .fold(Aabb::empty(), |bbox, &p| Aabb::surrounding(&bbox, &Aabb { min: p, max: p }))
+            .padded()
+    }
+
+    /// Creates the smallest box containing both `a` and `b`.
+    pub fn surrounding(a: &Aabb, b: &Aabb) -> Aabb {
+        Aabb {
+            min: Point3::new(
+                a.min.x().min(b.min.x()),
+                a.min.y().min(b.min.y()),
+                a.min.z().min(b.min.z()),
+            ),
+            max: Point3::new(
+                a.max.x().max(b.max.x()),
+                a.max.y().max(b.max.y()),
+                a.max.z().max(b.max.z()),
+            ),
+        }
+    }
+
+    /// The corner with the smallest coordinates.
+    pub fn min(&self) -> Point3 {
+        self.min
+    }
+
+    /// The corner with the largest coordinates.
+    pub fn max(&self) -> Point3 {
+        self.max
+    }
+
+    /// Returns true if the box contains nothing.
+    pub fn is_empty(&self) -> bool {
+        self.min.x() > self.max.x() || self.min.y() > self.max.y() || self.min.z() > self.max.z()
+    }
+
+    /// The center of the box.
+    pub fn centroid(&self) -> Point3 {
+        0.5 * (self.min + self.max)
+    }
+
+    /// The size of the box along each axis.
+    pub fn extent(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    /// The index (0 for x, 1 for y, 2 for z) of the axis along which the box is the longest.
+    pub fn longest_axis(&self) -> usize {
+        let e = self.extent();
+        if e.x() > e.y() && e.x() > e.z() {
+            0
+        } else if e.y() > e.z() {
+            1
+        } else {
+            2
+        }
+    }
+
+    /// The total area of the six faces of the box.
+    pub fn surface_area(&self) -> f64 {
+        if self.is_empty() {
+            return 0.0;
+        }
+        let e = self.extent();
+        2.0 * (e.x() * e.y() + e.y() * e.z() + e.z() * e.x())
+    }
+
+    /// Returns true if the ray enters the box between `t_min` and `t_max`.
+    ///
+    /// Uses the slab method: the ray is clipped against the pair of planes
+    /// bounding each axis, and hits the box if the three intervals overlap.
+    pub fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> bool {
+        let d = r.direction();
+        let inv_dir = Vec3::new(1.0 / d.x(), 1.0 / d.y(), 1.0 / d.z());
+        self.hit_inverse(r.origin(), inv_dir, t_min, t_max)
+    }
+
+    /// Same as `hit`, with the inverse of the ray direction computed once by the
+    /// caller, which saves three divisions per box when traversing a hierarchy.
+    pub(crate) fn hit_inverse(&self, origin: Point3, inv_dir: Vec3, mut t_min: f64, mut t_max: f64) -> bool {
+        for axis in 0..3 {
+            let inv_d = inv_dir[axis];
+            let mut t0 = (self.min[axis] - origin[axis]) * inv_d;
+            let mut t1 = (self.max[axis] - origin[axis]) * inv_d;
+            if inv_d < 0.0 {
+                std::mem::swap(&mut t0, &mut t1);
+            }
+
+            t_min = if t0 > t_min { t0 } else { t_min };
+            t_max = if t1 < t_max { t1 } else { t_max };
+            if t_max <= t_min {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Grows the box along the axes where it is thinner than `MIN_THICKNESS`.
+    fn padded(self) -> Aabb {
+        let delta = MIN_THICKNESS / 2.0;
+        let pad = |min: f64, max: f64| {
+            if max - min < MIN_THICKNESS {
+                (min - delta, max + delta)
+            } else {
+                (min, max)
+            }
+        };
+        let (x0, x1) = pad(self.min.x(), self.max.x());
+        let (y0, y1) = pad(self.min.y(), self.max.y());
+        let (z0, z1) = pad(self.min.z(), self.max.z());
+        Aabb {
+            min: Point3::new(x0, y0, z0),
+            max: Point3::new(x1, y1, z1),
+        }
+    }
+}
@@ -0,0 +1,68 @@
+/// An arbitrary output variable: what the camera rays see at their first hit,
+/// rendered along with the image for compositing and denoising.
+///
+/// Each one is stored as a few floats per pixel, its components, averaged over the
+/// samples of the pixel like the colors.
+///
+/// # Example
+///
+/// ```
+/// use raytracer::Aov;
+///
+/// assert_eq!(Aov::Normal.name(), "normal");
+/// assert_eq!(Aov::Normal.channels(), ["X", "Y", "Z"]);
+/// assert_eq!(Aov::Depth.components(), 1);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Aov {
+    /// The color of the surface, whatever its lighting: the texture of diffuse and
+    /// metal surfaces, white for glass and lights. Rays that miss see the background.
+    Albedo,
+    /// The shading normal, a unit vector facing the camera, or 0 where no sample hits.
+    Normal,
+    /// The hit point, in world coordinates.
+    Position,
+    /// The distance from the camera to the hit point, infinite where no sample hits.
+    Depth,
+    /// The texture coordinates of the hit point.
+    Uv,
+    /// A number per material, so that mattes can be cut around every object using it.
+    ///
+    /// Materials are numbered from 1 in the order they first appear, from the top-left
+    /// corner of the image, and 0 is the background. It is the material of the first
+    /// sample of the pixel rather than an average, which would mean nothing.
+    MaterialId,
+}
+
+impl Aov {
+    /// Every AOV, in the order they are described.
+    pub const ALL: [Aov; 6] = [Aov::Albedo, Aov::Normal, Aov::Position, Aov::Depth, Aov::Uv, Aov::MaterialId];
+
+    /// The name of the AOV: the layer of an OpenEXR image, and the suffix of its own files.
+    pub fn name(self) -> &'static str {
+        match self {
+            Aov::Albedo => "albedo",
+            Aov::Normal => "normal",
+            Aov::Position => "position",
+            Aov::Depth => "depth",
+            Aov::Uv => "uv",
+            Aov::MaterialId => "id",
+        }
+    }
+
+    /// The names of the components, the channels of its layer in an OpenEXR image.
+    pub fn channels(self) -> &'static [&'static str] {
+        match self {
+            Aov::Albedo => &["R", "G", "B"],
+            Aov::Normal | Aov::Position => &["X", "Y", "Z"],
+            Aov::Depth => &["Z"],
+            Aov::Uv => &["U", "V"],
+            Aov::MaterialId => &["ID"],
+        }
+    }
+
+    /// The number of floats per pixel.
+    pub fn components(self) -> usize {
+        self.channels().len()
+    }
+}
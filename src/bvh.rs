@@ -0,0 +1,488 @@
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::ray::Ray;
+use crate::vec3::Vec3;
+
+/// A node of a bounding volume hierarchy (BVH).
+///
+/// The objects of a scene are split recursively in two halves along the longest
+/// axis of their bounding box, forming a binary tree whose nodes store the box
+/// of everything below them. A ray missing a node's box skips the whole subtree,
+/// so finding the closest hit takes a logarithmic number of tests instead of a
+/// linear one.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use raytracer::{BvhNode, Color, Hittable, HittableList, Lambertian, Point3, Ray, Sphere, Vec3};
+///
+/// let material = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3)));
+/// let mut list = HittableList::new();
+/// for i in 0..100 {
+///     list.add(Box::new(Sphere::new(Point3::new(i as f64, 0.0, 0.0), 0.4, material.clone())));
+/// }
+///
+/// let bvh = BvhNode::new(list);
+/// let ray = Ray::new(Point3::new(42.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+/// assert!(bvh.hit(&ray, 0.001, f64::INFINITY).is_some());
+/// ```
+pub struct BvhNode {
+    /// The first child, an object or another node.
+    left: Box<dyn Hittable>,
+    /// The second child, absent when the node holds a single object.
+    right: Option<Box<dyn Hittable>>,
+    /// A box containing both children.
+    bbox: Aabb,
+}
+
+impl BvhNode {
+    /// Builds a hierarchy over every object of `list`.
+    pub fn new(list: HittableList) -> BvhNode {
+        let objects = list.into_objects();
+        if objects.is_empty() {
+            return BvhNode {
+                left: Box::new(HittableList::new()),
+                right: None,
+                bbox: Aabb::empty(),
+            };
+        }
+        Self::build(objects)
+    }
+
+    /// Splits `objects` at the median of their centers along the longest axis.
+    fn build(mut objects: Vec<Box<dyn Hittable>>) -> BvhNode {
+        let bbox = objects.iter().fold(Aabb::empty(), |bbox, object| {
+            Aabb::surrounding(&bbox, &object.bounding_box())
+        });
+
+        if objects.len() <= 2 {
+            let left = objects.remove(0);
+            let right = objects.pop();
+            return BvhNode { left, right, bbox };
+        }
+
+        let axis = bbox.longest_axis();
+        objects.sort_by(|a, b| {
+            let a = a.bounding_box().centroid()[axis];
+            let b = b.bounding_box().centroid()[axis];
+            a.total_cmp(&b)
+        });
+
+        let right = objects.split_off(objects.len() / 2);
+        BvhNode {
+            left: Box::new(Self::build(objects)),
+            right: Some(Box::new(Self::build(right))),
+            bbox,
+        }
+    }
+}
+
+impl Hittable for BvhNode {
+    /// Finds the closest hit in the children whose box is hit by the ray.
+    ///
+    /// The right child is searched only up to the closest hit found in the left one.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        if !self.bbox.hit(ray, t_min, t_max) {
+            return None;
+        }
+
+        let hit_left = self.left.hit(ray, t_min, t_max);
+        let closest = hit_left.as_ref().map_or(t_max, |rec| rec.t);
+        let hit_right = self
+            .right
+            .as_ref()
+            .and_then(|right| right.hit(ray, t_min, closest));
+
+        hit_right.or(hit_left)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
+
+/// Number of buckets the centers are binned into when looking for the best split.
+const SAH_BUCKETS: usize = 16;
+/// Largest number of primitives a leaf may hold.
+const MAX_LEAF_SIZE: usize = 4;
+/// Cost of testing a ray against a node's box, relative to testing one primitive.
+const TRAVERSAL_COST: f64 = 0.125;
+/// Depth of the traversal stack kept on the call stack. Deeper hierarchies, built over
+/// very uneven scenes, spill the rest of their nodes into a `Vec`.
+const STACK_SIZE: usize = 64;
+
+/// A node of a [`FlatBvh`], stored in a contiguous array.
+///
+/// The first child of an interior node is the node right after it in the array;
+/// only the index of the second child is stored.
+#[derive(Debug, Clone, Copy)]
+struct LinearNode {
+    bbox: Aabb,
+    /// Leaf: index of the first primitive. Interior node: index of the second child.
+    offset: u32,
+    /// Number of primitives of a leaf, 0 for an interior node.
+    count: u16,
+    /// Axis the children of an interior node were split along.
+    axis: u8,
+}
+
+/// A bounding volume hierarchy built with the surface area heuristic (SAH),
+/// flattened into an array of nodes.
+///
+/// The hierarchy only knows the bounding boxes of the primitives it was built
+/// from; its owner keeps the primitives, sorted in the order given by
+/// [`FlatBvh::build`], and tests them in the callback passed to [`FlatBvh::hit`].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct FlatBvh {
+    nodes: Vec<LinearNode>,
+}
+
+/// A primitive being sorted into the hierarchy.
+#[derive(Clone, Copy)]
+struct BuildPrimitive {
+    index: usize,
+    bbox: Aabb,
+    centroid: [f64; 3],
+}
+
+#[derive(Clone, Copy)]
+struct Bucket {
+    count: usize,
+    bbox: Aabb,
+}
+
+impl FlatBvh {
+    /// Builds a hierarchy over primitives having the given bounding boxes.
+    ///
+    /// Returns the hierarchy and the order in which the primitives must be stored:
+    /// the `i`-th primitive seen by [`FlatBvh::hit`] is `boxes[order[i]]`.
+    pub(crate) fn build(boxes: &[Aabb]) -> (FlatBvh, Vec<usize>) {
+        let mut primitives: Vec<BuildPrimitive> = boxes
+            .iter()
+            .enumerate()
+            .map(|(index, bbox)| {
+                let c = bbox.centroid();
+                BuildPrimitive {
+                    index,
+                    bbox: *bbox,
+                    centroid: [c.x(), c.y(), c.z()],
+                }
+            })
+            .collect();
+
+        let mut bvh = FlatBvh {
+            nodes: Vec::with_capacity(2 * boxes.len()),
+        };
+        if !primitives.is_empty() {
+            bvh.build_nodes(&mut primitives);
+        }
+
+        let order = primitives.iter().map(|p| p.index).collect();
+        (bvh, order)
+    }
+
+    /// The box containing every primitive.
+    pub(crate) fn bounding_box(&self) -> Aabb {
+        self.nodes.first().map_or(Aabb::empty(), |node| node.bbox)
+    }
+
+    /// Appends the nodes of the hierarchy over `primitives`, reordering them so that
+    /// every leaf refers to a contiguous range.
+    ///
+    /// Nodes are built depth first with a stack of ranges left to build rather than
+    /// by recursion, since very uneven scenes give hierarchies deep enough to
+    /// overflow the call stack.
+    fn build_nodes(&mut self, primitives: &mut [BuildPrimitive]) {
+        // Ranges of primitives, with the parent whose second child they are
+        let mut pending = vec![(0..primitives.len(), None::<usize>)];
+
+        while let Some((range, parent)) = pending.pop() {
+            let node_index = self.nodes.len();
+            if let Some(parent) = parent {
+                self.nodes[parent].offset = node_index as u32;
+            }
+            let first = range.start;
+            let primitives = &mut primitives[range];
+            let bbox = primitives.iter().fold(Aabb::empty(), |bbox, p| Aabb::surrounding(&bbox, &p.bbox));
+            self.nodes.push(LinearNode {
+                bbox,
+                offset: first as u32,
+                count: primitives.len() as u16,
+                axis: 0,
+            });
+
+            if primitives.len() == 1 {
+                continue;
+            }
+
+            let centroid_bounds = primitives.iter().fold(Aabb::empty(), |bounds, p| {
+                let c = Vec3::new(p.centroid[0], p.centroid[1], p.centroid[2]);
+                Aabb::surrounding(&bounds, &Aabb::from_corners(c, c))
+            });
+
+            let mid = match Self::sah_split(primitives, &bbox, &centroid_bounds) {
+                Some((axis, mid)) => {
+                    self.nodes[node_index].axis = axis as u8;
+                    mid
+                }
+                None if primitives.len() <= MAX_LEAF_SIZE => continue,
+                None => {
+                    // Splitting does not pay off but the leaf would be too large,
+                    // or all centers are at the same place: split in two halves
+                    let axis = centroid_bounds.longest_axis();
+                    let mid = primitives.len() / 2;
+                    primitives.select_nth_unstable_by(mid, |a, b| {
+                        a.centroid[axis].total_cmp(&b.centroid[axis])
+                    });
+                    self.nodes[node_index].axis = axis as u8;
+                    mid
+                }
+            };
+
+            // The first child comes right after its parent, so it is built first
+            self.nodes[node_index].count = 0;
+            let end = first + primitives.len();
+            pending.push((first + mid..end, Some(node_index)));
+            pending.push((first..first + mid, None));
+        }
+    }
+
+    /// Looks for the cheapest split according to the surface area heuristic.
+    ///
+    /// Centers are binned into `SAH_BUCKETS` buckets along each axis, and every
+    /// boundary between buckets is evaluated. Returns the axis and the number of
+    /// primitives moved to the left side, or `None` when a leaf is cheaper.
+    fn sah_split(
+        primitives: &mut [BuildPrimitive],
+        bbox: &Aabb,
+        centroid_bounds: &Aabb,
+    ) -> Option<(usize, usize)> {
+        let count = primitives.len();
+        let min = centroid_bounds.min();
+        let extent = centroid_bounds.extent();
+        let bucket_of = |p: &BuildPrimitive, axis: usize| {
+            let b = (SAH_BUCKETS as f64 * (p.centroid[axis] - min[axis]) / extent[axis]) as usize;
+            b.min(SAH_BUCKETS - 1)
+        };
+
+        let mut best: Option<(f64, usize, usize)> = None;
+        for axis in 0..3 {
+            if extent[axis] <= 0.0 {
+                continue;
+            }
+
+            let mut buckets = [Bucket {
+                count: 0,
+                bbox: Aabb::empty(),
+            }; SAH_BUCKETS];
+            for p in primitives.iter() {
+                let bucket = &mut buckets[bucket_of(p, axis)];
+                bucket.count += 1;
+                bucket.bbox = Aabb::surrounding(&bucket.bbox, &p.bbox);
+            }
+
+            // Sweep from the right to know the cost of everything after each boundary
+            let mut right_area = [0.0; SAH_BUCKETS];
+            let mut right_count = [0; SAH_BUCKETS];
+            let mut acc = Aabb::empty();
+            let mut n = 0;
+            for i in (1..SAH_BUCKETS).rev() {
+                acc = Aabb::surrounding(&acc, &buckets[i].bbox);
+                n += buckets[i].count;
+                right_area[i] = acc.surface_area();
+                right_count[i] = n;
+            }
+
+            let mut acc = Aabb::empty();
+            let mut n = 0;
+            for split in 1..SAH_BUCKETS {
+                acc = Aabb::surrounding(&acc, &buckets[split - 1].bbox);
+                n += buckets[split - 1].count;
+                if n == 0 || right_count[split] == 0 {
+                    continue;
+                }
+                let cost = n as f64 * acc.surface_area() + right_count[split] as f64 * right_area[split];
+                if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                    best = Some((cost, axis, split));
+                }
+            }
+        }
+
+        let (cost, axis, split) = best?;
+        let split_cost = TRAVERSAL_COST + cost / bbox.surface_area().max(f64::MIN_POSITIVE);
+        if count <= MAX_LEAF_SIZE && split_cost >= count as f64 {
+            return None;
+        }
+
+        // Move the primitives of the buckets before the split to the front
+        let mut mid = 0;
+        for i in 0..count {
+            if bucket_of(&primitives[i], axis) < split {
+                primitives.swap(i, mid);
+                mid += 1;
+            }
+        }
+        Some((axis, mid))
+    }
+
+    /// Finds the closest hit between `t_min` and `t_max`.
+    ///
+    /// `hit_primitive(i, ray, t_min, t_max)` tests the `i`-th primitive in build order.
+    /// Children are visited near to far according to the sign of the ray direction
+    /// along their split axis, which shrinks `t_max` early and prunes more nodes.
+    pub(crate) fn hit<F>(&self, ray: &Ray, t_min: f64, t_max: f64, mut hit_primitive: F) -> Option<HitRecord>
+    where
+        F: FnMut(usize, &Ray, f64, f64) -> Option<HitRecord>,
+    {
+        if self.nodes.is_empty() {
+            return None;
+        }
+
+        let d = ray.direction();
+        let inv_dir = Vec3::new(1.0 / d.x(), 1.0 / d.y(), 1.0 / d.z());
+        let dir_is_neg = [inv_dir.x() < 0.0, inv_dir.y() < 0.0, inv_dir.z() < 0.0];
+        let origin = ray.origin();
+
+        let mut closest = t_max;
+        let mut result = None;
+        let mut stack = [0u32; STACK_SIZE];
+        let mut stack_len = 0;
+        // Only allocates once the array is full, and holds the nodes above it
+        let mut overflow = Vec::new();
+        let mut current = 0;
+
+        loop {
+            let node = &self.nodes[current];
+            if node.bbox.hit_inverse(origin, inv_dir, t_min, closest) {
+                if node.count > 0 {
+                    let first = node.offset as usize;
+                    for i in first..first + node.count as usize {
+                        if let Some(rec) = hit_primitive(i, ray, t_min, closest) {
+                            closest = rec.t;
+                            result = Some(rec);
+                        }
+                    }
+                } else {
+                    // Visit the near child first, keep the far one for later
+                    let (near, far) = if dir_is_neg[node.axis as usize] {
+                        (node.offset as usize, current + 1)
+                    } else {
+                        (current + 1, node.offset as usize)
+                    };
+                    if stack_len < STACK_SIZE {
+                        stack[stack_len] = far as u32;
+                        stack_len += 1;
+                    } else {
+                        overflow.push(far as u32);
+                    }
+                    current = near;
+                    continue;
+                }
+            }
+
+            if let Some(next) = overflow.pop() {
+                current = next as usize;
+            } else if stack_len > 0 {
+                stack_len -= 1;
+                current = stack[stack_len] as usize;
+            } else {
+                break;
+            }
+        }
+
+        result
+    }
+}
+
+/// A bounding volume hierarchy over the objects of a [`HittableList`], built with
+/// the surface area heuristic and stored as a flat array of nodes.
+///
+/// It is a drop-in replacement for the list it is built from, and is usually both
+/// faster to build and faster to traverse than [`BvhNode`].
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use raytracer::{Bvh, Color, Hittable, HittableList, Lambertian, Point3, Ray, Sphere, Vec3};
+///
+/// let material = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3)));
+/// let mut list = HittableList::new();
+/// for i in 0..100 {
+///     list.add(Box::new(Sphere::new(Point3::new(i as f64, 0.0, 0.0), 0.4, material.clone())));
+/// }
+///
+/// let bvh = Bvh::new(list);
+/// let ray = Ray::new(Point3::new(42.0, 5.0, 0.0), Vec3::new(0.0, -1.0, 0.0));
+/// let rec = bvh.hit(&ray, 0.001, f64::INFINITY).unwrap();
+/// assert!((rec.p.y() - 0.4).abs() < 1e-9);
+/// ```
+///
+/// Scenes where sizes and distances grow geometrically give hierarchies far deeper
+/// than balanced ones, which are still built and traversed without exhausting the stack:
+///
+/// ```
+/// use std::sync::Arc;
+/// use raytracer::{Bvh, Color, Hittable, HittableList, Lambertian, Point3, Ray, Sphere, Vec3};
+///
+/// let spheres = || {
+///     let material = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
+///     let mut list = HittableList::new();
+///     for i in 0..20_000 {
+///         let x = 1.01f64.powi(i);
+///         list.add(Box::new(Sphere::new(Point3::new(x, 0.0, 0.0), 0.01 * x, material.clone())));
+///     }
+///     list
+/// };
+/// let list = spheres();
+/// // On a thread with a small stack, which a recursive build would overflow
+/// let bvh = std::thread::Builder::new()
+///     .stack_size(64 * 1024)
+///     .spawn(move || Bvh::new(spheres()))
+///     .unwrap()
+///     .join()
+///     .unwrap();
+///
+/// for i in (0..20_000).step_by(97) {
+///     let x = 1.01f64.powf(i as f64 + 0.5);
+///     let ray = Ray::new(Point3::new(x, x, 0.0), Vec3::new(0.0, -1.0, 0.0));
+///     let expected = list.hit(&ray, 0.001, f64::INFINITY).map(|rec| rec.t);
+///     assert_eq!(bvh.hit(&ray, 0.001, f64::INFINITY).map(|rec| rec.t), expected);
+/// }
+/// ```
+pub struct Bvh {
+    /// The objects, sorted so that every leaf refers to a contiguous range.
+    objects: Vec<Box<dyn Hittable>>,
+    nodes: FlatBvh,
+}
+
+impl Bvh {
+    /// Builds a hierarchy over every object of `list`.
+    pub fn new(list: HittableList) -> Bvh {
+        let objects = list.into_objects();
+        let boxes: Vec<Aabb> = objects.iter().map(|object| object.bounding_box()).collect();
+        let (nodes, order) = FlatBvh::build(&boxes);
+
+        let mut slots: Vec<Option<Box<dyn Hittable>>> = objects.into_iter().map(Some).collect();
+        let objects = order
+            .into_iter()
+            .map(|i| slots[i].take().expect("each object is placed once"))
+            .collect();
+
+        Bvh { objects, nodes }
+    }
+}
+
+impl Hittable for Bvh {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.nodes.hit(ray, t_min, t_max, |i, ray, t_min, t_max| {
+            self.objects[i].hit(ray, t_min, t_max)
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.nodes.bounding_box()
+    }
+}
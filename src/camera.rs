@@ -12,6 +12,7 @@ use crate::vec3::{self, Point3, Vec3};
 /// - `u`: The camera's horizontal axis in world space.
 /// - `v`: The camera's vertical axis in world space.
 /// - `lens_radius`: The radius of the camera's lens.
+/// - `aspect_ratio`: The aspect ratio of the viewport.
 ///
 /// # Methods
 /// 
@@ -48,6 +49,7 @@ pub struct Camera {
     u: Vec3,
     v: Vec3,
     lens_radius: f64,
+    aspect_ratio: f64,
 }
 
 impl Camera {
@@ -109,8 +111,24 @@ impl Camera {
             u,
             v,
             lens_radius,
+            aspect_ratio,
         }
     }
+
+    /// Returns the aspect ratio (width / height) of the viewport.
+    pub fn aspect_ratio(&self) -> f64 {
+        self.aspect_ratio
+    }
+
+    /// Changes the aspect ratio of the viewport, keeping its center and vertical field-of-view.
+    ///
+    /// Useful to render a scene at a resolution other than the one it was built for.
+    pub fn set_aspect_ratio(&mut self, aspect_ratio: f64) {
+        let center = self.lower_left_corner + self.horizontal / 2.0 + self.vertical / 2.0;
+        self.horizontal *= aspect_ratio / self.aspect_ratio;
+        self.lower_left_corner = center - self.horizontal / 2.0 - self.vertical / 2.0;
+        self.aspect_ratio = aspect_ratio;
+    }
  
     /// Generate a ray starting from a defined camera and going to the given coordinates.
     /// 
@@ -0,0 +1,277 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use raytracer::{Aov, Background, Color, ExrCompression, ImageFormat, ToneMap};
+
+pub const USAGE: &str = "\
+Usage:
+    raytracer render <scene> [options]
+    raytracer list-scenes
+    raytracer help
+
+<scene> is a JSON scene file or the name of a built-in scene (see list-scenes).
+
+Render options:
+    -o, --output <file>       Output image, a .png, .ppm, .pfm or .exr file [default: <scene name>.png]
+    -f, --format <format>     \"png\", \"ppm\" (binary), \"ppm-ascii\", \"pfm\" or \"exr\"
+                              [default: from the extension]
+        --compression <c>     Compression of EXR images: \"none\", \"zip\" or \"piz\" [default: zip]
+        --alpha               Add the coverage of the pixels as alpha to EXR images
+        --exposure <ev>       Exposure of PNG and PPM images, in stops [default: 0]
+        --tonemap <op>        \"clamp\", \"reinhard\", \"reinhard-extended\", \"hable\" or \"aces\"
+                              [default: clamp]
+        --white <w>           Radiance that becomes white with reinhard-extended [default: 4]
+        --aov <list>          Also render AOVs, separated by commas: \"albedo\", \"normal\",
+                              \"position\", \"depth\", \"uv\" and \"id\", or \"all\". They are
+                              layers of EXR images, else <output>.<aov>.pfm files next to
+                              PFM images and <output>.<aov>.exr files next to the others
+        --denoise             Filter the noise out of the image, guided by its albedo and normals
+    -w, --width <pixels>      Image width [default: 600]
+        --aspect-ratio <r>    Width / height ratio [default: the scene camera's]
+    -s, --spp <n>             Samples per pixel [default: 100]
+    -d, --depth <n>           Maximum number of bounces [default: 25]
+    -t, --threads <n>         Number of worker threads [default: all cores]
+        --seed <n>            Seed of the random generator, for reproducible renders
+        --background <bg>     \"sky\" or a color as r,g,b [default: the scene's]";
+
+/// Radiance that becomes white with the extended Reinhard operator, unless --white says otherwise.
+const DEFAULT_WHITE: f64 = 4.0;
+
+/// What the user asked the binary to do.
+pub enum Command {
+    Render(Box<RenderArgs>),
+    ListScenes,
+    Help,
+}
+
+/// Options of the `render` subcommand. `None` means "use the default".
+pub struct RenderArgs {
+    pub scene: String,
+    pub output: Option<PathBuf>,
+    pub format: Option<ImageFormat>,
+    pub compression: Option<ExrCompression>,
+    pub alpha: bool,
+    pub exposure: Option<f64>,
+    pub tone_map: Option<ToneMap>,
+    pub aovs: Vec<Aov>,
+    pub denoise: bool,
+    pub width: Option<usize>,
+    pub aspect_ratio: Option<f64>,
+    pub samples_per_pixel: Option<u32>,
+    pub max_depth: Option<u32>,
+    pub threads: Option<usize>,
+    pub seed: Option<u64>,
+    pub background: Option<Background>,
+}
+
+/// Error in the command line, displayed along with the usage.
+#[derive(Debug)]
+pub struct CliError(String);
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Parses the command line arguments, without the program name.
+pub fn parse<I: IntoIterator<Item = String>>(args: I) -> Result<Command, CliError> {
+    let mut args = args.into_iter();
+
+    match args.next().as_deref() {
+        Some("render") => parse_render(args).map(|render| Command::Render(Box::new(render))),
+        Some("list-scenes") => match args.next() {
+            None => Ok(Command::ListScenes),
+            Some(arg) => Err(CliError(format!("unexpected argument '{}'", arg))),
+        },
+        Some("help" | "-h" | "--help") | None => Ok(Command::Help),
+        Some(other) => Err(CliError(format!("unknown command '{}'", other))),
+    }
+}
+
+fn parse_render<I: Iterator<Item = String>>(mut args: I) -> Result<RenderArgs, CliError> {
+    let mut scene = None;
+    let mut white_point = None;
+    let mut render = RenderArgs {
+        scene: String::new(),
+        output: None,
+        format: None,
+        compression: None,
+        alpha: false,
+        exposure: None,
+        tone_map: None,
+        aovs: Vec::new(),
+        denoise: false,
+        width: None,
+        aspect_ratio: None,
+        samples_per_pixel: None,
+        max_depth: None,
+        threads: None,
+        seed: None,
+        background: None,
+    };
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "-o" | "--output" => render.output = Some(value(&arg, args.next())?),
+            "-f" | "--format" => render.format = Some(format(&arg, args.next())?),
+            "--compression" => render.compression = Some(compression(&arg, args.next())?),
+            "--alpha" => render.alpha = true,
+            "--exposure" => {
+                let exposure: f64 = value(&arg, args.next())?;
+                if !exposure.is_finite() {
+                    return Err(CliError(format!("{} must be a number", arg)));
+                }
+                render.exposure = Some(exposure);
+            }
+            "--tonemap" => render.tone_map = Some(tone_map(&arg, args.next())?),
+            "--white" => {
+                let white: f64 = value(&arg, args.next())?;
+                if white <= 0.0 || !white.is_finite() {
+                    return Err(CliError(format!("{} must be a positive number", arg)));
+                }
+                white_point = Some(white);
+            }
+            "--aov" => render.aovs = aovs(&arg, args.next())?,
+            "--denoise" => render.denoise = true,
+            "-w" | "--width" => render.width = Some(positive(&arg, args.next())?),
+            "--aspect-ratio" => {
+                let ratio: f64 = value(&arg, args.next())?;
+                if ratio <= 0.0 || !ratio.is_finite() {
+                    return Err(CliError(format!("{} must be a positive number", arg)));
+                }
+                render.aspect_ratio = Some(ratio);
+            }
+            "-s" | "--spp" => render.samples_per_pixel = Some(positive(&arg, args.next())?),
+            "-d" | "--depth" => render.max_depth = Some(positive(&arg, args.next())?),
+            "-t" | "--threads" => render.threads = Some(positive(&arg, args.next())?),
+            "--seed" => render.seed = Some(value(&arg, args.next())?),
+            "--background" => render.background = Some(background(&arg, args.next())?),
+            flag if flag.starts_with('-') => {
+                return Err(CliError(format!("unknown option '{}'", flag)))
+            }
+            _ if scene.is_none() => scene = Some(arg),
+            _ => return Err(CliError(format!("unexpected argument '{}'", arg))),
+        }
+    }
+
+    render.scene = scene.ok_or_else(|| CliError("render needs a scene".to_string()))?;
+    match (&mut render.tone_map, white_point) {
+        (Some(ToneMap::ReinhardExtended { white }), _) => *white = white_point.unwrap_or(DEFAULT_WHITE),
+        (_, Some(_)) => return Err(CliError("--white needs --tonemap reinhard-extended".to_string())),
+        _ => {}
+    }
+    Ok(render)
+}
+
+/// Parses the value following the option `name`.
+fn value<T: FromStr>(name: &str, value: Option<String>) -> Result<T, CliError> {
+    let value = value.ok_or_else(|| CliError(format!("{} needs a value", name)))?;
+    value
+        .parse()
+        .map_err(|_| CliError(format!("invalid value '{}' for {}", value, name)))
+}
+
+/// Parses the value following the option `name`, which must be a non-zero integer.
+fn positive<T: FromStr + Default + PartialEq>(
+    name: &str,
+    arg: Option<String>,
+) -> Result<T, CliError> {
+    let n: T = value(name, arg)?;
+    if n == T::default() {
+        return Err(CliError(format!("{} must be greater than 0", name)));
+    }
+    Ok(n)
+}
+
+/// Parses the value following the option `name` as a background: `sky` or `r,g,b`.
+fn background(name: &str, arg: Option<String>) -> Result<Background, CliError> {
+    let arg = arg.ok_or_else(|| CliError(format!("{} needs a value", name)))?;
+    if arg == "sky" {
+        return Ok(Background::Sky);
+    }
+    let components: Vec<f64> = arg
+        .split(',')
+        .map(|c| c.trim().parse().ok().filter(|c: &f64| *c >= 0.0))
+        .collect::<Option<_>>()
+        .ok_or_else(|| CliError(format!("invalid value '{}' for {}", arg, name)))?;
+    match components[..] {
+        [r, g, b] => Ok(Background::Solid(Color::new(r, g, b))),
+        _ => Err(CliError(format!(
+            "{} must be \"sky\" or a color as r,g,b",
+            name
+        ))),
+    }
+}
+
+/// Parses the value following the option `name` as an image format.
+fn format(name: &str, arg: Option<String>) -> Result<ImageFormat, CliError> {
+    let arg = arg.ok_or_else(|| CliError(format!("{} needs a value", name)))?;
+    match arg.as_str() {
+        "png" => Ok(ImageFormat::Png),
+        "ppm" => Ok(ImageFormat::Ppm),
+        "ppm-ascii" => Ok(ImageFormat::PpmAscii),
+        "pfm" => Ok(ImageFormat::Pfm),
+        "exr" => Ok(ImageFormat::Exr {
+            compression: ExrCompression::default(),
+            alpha: false,
+        }),
+        _ => Err(CliError(format!(
+            "{} must be \"png\", \"ppm\", \"ppm-ascii\", \"pfm\" or \"exr\"",
+            name
+        ))),
+    }
+}
+
+/// Parses the value following the option `name` as an EXR compression.
+fn compression(name: &str, arg: Option<String>) -> Result<ExrCompression, CliError> {
+    let arg = arg.ok_or_else(|| CliError(format!("{} needs a value", name)))?;
+    match arg.as_str() {
+        "none" => Ok(ExrCompression::None),
+        "zip" => Ok(ExrCompression::Zip),
+        "piz" => Ok(ExrCompression::Piz),
+        _ => Err(CliError(format!(
+            "{} must be \"none\", \"zip\" or \"piz\"",
+            name
+        ))),
+    }
+}
+
+/// Parses the value following the option `name` as a tone mapping operator.
+fn tone_map(name: &str, arg: Option<String>) -> Result<ToneMap, CliError> {
+    let arg = arg.ok_or_else(|| CliError(format!("{} needs a value", name)))?;
+    match arg.as_str() {
+        "clamp" => Ok(ToneMap::Clamp),
+        "reinhard" => Ok(ToneMap::Reinhard),
+        // The white point is set from --white once every option is read
+        "reinhard-extended" => Ok(ToneMap::ReinhardExtended { white: DEFAULT_WHITE }),
+        "hable" => Ok(ToneMap::Hable),
+        "aces" => Ok(ToneMap::Aces),
+        _ => Err(CliError(format!(
+            "{} must be \"clamp\", \"reinhard\", \"reinhard-extended\", \"hable\" or \"aces\"",
+            name
+        ))),
+    }
+}
+
+/// Parses the value following the option `name` as a list of AOVs separated by commas.
+fn aovs(name: &str, arg: Option<String>) -> Result<Vec<Aov>, CliError> {
+    let arg = arg.ok_or_else(|| CliError(format!("{} needs a value", name)))?;
+    if arg == "all" {
+        return Ok(Aov::ALL.to_vec());
+    }
+    let mut aovs = Vec::new();
+    for item in arg.split(',').map(str::trim) {
+        let aov = Aov::ALL.into_iter().find(|aov| aov.name() == item).ok_or_else(|| {
+            CliError(format!(
+                "{} takes \"albedo\", \"normal\", \"position\", \"depth\", \"uv\" and \"id\", or \"all\", not '{}'",
+                name, item
+            ))
+        })?;
+        if !aovs.contains(&aov) {
+            aovs.push(aov);
+        }
+    }
+    Ok(aovs)
+}
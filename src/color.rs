@@ -1,29 +1,22 @@
 use std::io::Write;
  
-use crate::common;
+use crate::tonemap::DisplayTransform;
 use crate::vec3::Vec3;
  
 // Type alias
 pub type Color = Vec3;
  
 pub fn write_color(out: &mut impl Write, pixel_color: Color, samples_per_pixel: i32) {
-    let mut r = pixel_color.x();
-    let mut g = pixel_color.y();
-    let mut b = pixel_color.z();
- 
-    // Divide the color by the number of samples and gamma-correct for gamma=2.0
-    let scale = 1.0 / samples_per_pixel as f64;
-    r = f64::sqrt(scale * r);
-    g = f64::sqrt(scale * g);
-    b = f64::sqrt(scale * b);
- 
+    let [r, g, b] = to_rgb8(pixel_color, samples_per_pixel);
+
     // Write the translated [0, 255] value of each color component
-    writeln!(
-        out,
-        "{} {} {}",
-        (256.0 * common::clamp(r, 0.0, 0.999)) as i32,
-        (256.0 * common::clamp(g, 0.0, 0.999)) as i32,
-        (256.0 * common::clamp(b, 0.0, 0.999)) as i32,
-    )
-    .expect("writing color");
-}
\ No newline at end of file
+    writeln!(out, "{} {} {}", r, g, b).expect("writing color");
+}
+
+/// Converts the sum of `samples_per_pixel` samples into 8-bit sRGB components,
+/// averaged and clamped by the default [`DisplayTransform`].
+pub fn to_rgb8(pixel_color: Color, samples_per_pixel: i32) -> [u8; 3] {
+    // Divide the color by the number of samples
+    let scale = 1.0 / samples_per_pixel as f64;
+    DisplayTransform::default().to_rgb8(scale * pixel_color)
+}
@@ -1,8 +1,18 @@
 // Constants
 #![allow(unused_imports)]
 pub use std::f64::consts::PI;
-pub use std::f64::INFINITY;
- 
+pub const INFINITY: f64 = f64::INFINITY;
+
+use std::cell::RefCell;
+
+use rand::rngs::SmallRng;
+use rand::{Rng, SeedableRng};
+
+thread_local! {
+    // Every thread draws from its own generator, seeded from the OS unless `seed_random` is called
+    static RNG: RefCell<SmallRng> = RefCell::new(SmallRng::from_os_rng());
+}
+
 // Utility functions
 
 /// Convert degrees to radians
@@ -21,7 +31,26 @@ pub fn degrees_to_radians(degrees: f64) -> f64 {
 
 pub fn random_double() -> f64 {
     // Return a random real in [0.0, 1.0)
-    rand::random()
+    RNG.with(|rng| rng.borrow_mut().random())
+}
+
+/// Reseeds the random generator of the calling thread.
+///
+/// Every following call to `random_double` on this thread returns the same
+/// sequence for the same seed, which makes renders reproducible.
+///
+/// # Exemples
+///
+/// ```
+/// use raytracer::common::*;
+///
+/// seed_random(42);
+/// let a = random_double();
+/// seed_random(42);
+/// assert_eq!(a, random_double());
+/// ```
+pub fn seed_random(seed: u64) {
+    RNG.with(|rng| *rng.borrow_mut() = SmallRng::seed_from_u64(seed));
 }
  
 pub fn random_double_range(min: f64, max: f64) -> f64 {
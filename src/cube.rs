@@ -2,21 +2,22 @@ use crate::vec3::*;
 use crate::material::*;
 use std::sync::Arc;
 use crate::hittable::Hittable;
+use crate::aabb::Aabb;
 use crate::HittableList;
 use crate::quad::Quad;
 
 /// A cube represented as a collection of six `Quad` faces.
 /// This struct implements the `Hittable` trait, allowing it to be used in ray tracing.
 ///
+/// Each face has its own texture coordinates, running from 0 to 1 across it.
+///
 /// # Example
 ///
 /// ```
 /// use std::sync::Arc;
-/// use crate::vec3::Point3;
-/// use crate::material::Lambertian;
-/// use crate::Cube;
+/// use raytracer::{Color, Cube, Lambertian, Point3};
 ///
-/// let material = Arc::new(Lambertian::new(crate::vec3::Color::new(0.8, 0.3, 0.3)));
+/// let material = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3)));
 /// let cube = Cube::new(Point3::new(0.0, 0.0, 0.0), Point3::new(1.0, 1.0, 1.0), material);
 /// ```
 pub struct Cube {
@@ -40,9 +41,12 @@ impl Cube {
     /// # Example
     ///
     /// ```
+    /// # use std::sync::Arc;
+    /// # use raytracer::{Color, Cube, Lambertian, Point3};
+    /// # let material = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3)));
     /// let min = Point3::new(0.0, 0.0, 0.0);
     /// let max = Point3::new(1.0, 1.0, 1.0);
-    /// let cube = Cube::new(min, max, Arc::new(SomeMaterial));
+    /// let cube = Cube::new(min, max, material);
     /// ```
     pub fn new(min: Point3, max: Point3, mat: Arc<dyn Material>) -> Self {
         let mut sides = HittableList::new();
@@ -51,8 +55,13 @@ impl Cube {
         let dy = Vec3::new(0.0, max.y() - min.y(), 0.0);
         let dz = Vec3::new(0.0, 0.0, max.z() - min.z());
 
+        // Every face is laid out so that its normal points out of the cube, and its
+        // texture coordinates read the right way round when seen from outside
+
         // Front face
-        sides.add(Box::new(Quad::new(min, dx, dy, mat.clone())));
+        sides.add(Box::new(Quad::new(
+            Point3::new(max.x(), min.y(), min.z()), -dx, dy, mat.clone(),
+        )));
         // Back face
         sides.add(Box::new(Quad::new(
             Point3::new(min.x(), min.y(), max.z()), dx, dy, mat.clone(),
@@ -61,13 +70,13 @@ impl Cube {
         sides.add(Box::new(Quad::new(min, dz, dy, mat.clone())));
         // Right face
         sides.add(Box::new(Quad::new(
-            Point3::new(max.x(), min.y(), min.z()), dz, dy, mat.clone(),
+            Point3::new(max.x(), min.y(), max.z()), -dz, dy, mat.clone(),
         )));
         // Bottom face
         sides.add(Box::new(Quad::new(min, dx, dz, mat.clone())));
         // Top face
         sides.add(Box::new(Quad::new(
-            Point3::new(min.x(), max.y(), min.z()), dx, dz, mat.clone(),
+            Point3::new(min.x(), max.y(), max.z()), dx, -dz, mat.clone(),
         )));
 
         Cube { sides }
@@ -88,9 +97,12 @@ impl Cube {
     /// # Example
     ///
     /// ```
+    /// # use std::sync::Arc;
+    /// # use raytracer::{Color, Cube, Lambertian, Point3};
+    /// # let material = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3)));
     /// let center = Point3::new(0.0, 0.0, 0.0);
     /// let size = 2.0;
-    /// let cube = Cube::from_center(center, size, Arc::new(SomeMaterial));
+    /// let cube = Cube::from_center(center, size, material);
     /// ```
     pub fn from_center(center: Point3, size: f64, mat: Arc<dyn Material>) -> Self {
         let half_size = size / 2.0;
@@ -113,6 +125,14 @@ impl Hittable for Cube {
     ///
     /// An `Option<HitRecord>` containing intersection data if a hit occurs.
     fn hit(&self, ray: &crate::ray::Ray, t_min: f64, t_max: f64) -> Option<crate::hittable::HitRecord> {
+        // Skip the faces altogether when the ray misses the cube
+        if !self.sides.bounding_box().hit(ray, t_min, t_max) {
+            return None;
+        }
         self.sides.hit(ray, t_min, t_max)
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.sides.bounding_box()
+    }
 }
@@ -1,98 +1,188 @@
 use crate::vec3::*;
 use crate::material::*;
 use std::sync::Arc;
-use crate::hittable::Hittable;
-use crate::hittable_list::HittableList;
-use crate::quad::Quad;
-use crate::disk::Disk;
+use crate::hittable::{HitRecord, Hittable};
+use crate::aabb::Aabb;
+use crate::ray::Ray;
+use crate::disk;
+use crate::vec2::UV;
 
-/// A cylinder shape composed of multiple quads and two disks.
-/// The cylinder is centered around a given point, with a specified radius, height, and material.
+/// A cylinder intersected exactly, as a quadric surface, with smooth normals.
+///
+/// It stands upright along the Y axis, or along any axis with [`Cylinder::with_axis`]
+/// and [`Cylinder::between`]. The cylinder is closed by two flat caps unless it is
+/// made open with [`Cylinder::capped`], in which case its inside can be seen
+/// through the ends.
+/// See [`FacetedCylinder`](crate::FacetedCylinder) for a low-poly prism.
 ///
 /// # Example
 ///
 /// ```
 /// use std::sync::Arc;
-/// use crate::vec3::Point3;
-/// use crate::material::Lambertian;
-/// use crate::cylinder::Cylinder;
+/// use raytracer::{Color, Cylinder, Lambertian, Point3};
 ///
-/// let material = Arc::new(Lambertian::new(crate::vec3::Color::new(0.8, 0.3, 0.3)));
-/// let cylinder = Cylinder::new(Point3::new(0.0, 0.0, 0.0), 1.0, 2.0, material, 16);
+/// let material = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3)));
+/// let pipe = Cylinder::new(Point3::new(0.0, 0.0, 0.0), 0.5, 2.0, material).capped(false);
 /// ```
 pub struct Cylinder {
-    /// The list of hittable objects that make up the cylinder (quads for the sides and disks for the top and bottom).
-    pub sides: HittableList,
+    /// The center of the bottom end.
+    base: Point3,
+    /// Unit vector from the bottom end to the top end.
+    axis: Vec3,
+    radius: f64,
+    height: f64,
+    /// Whether the ends are closed by disks.
+    capped: bool,
+    mat: Arc<dyn Material>,
 }
 
 impl Cylinder {
-    /// Creates a new cylinder with a given center, radius, height, material, and number of segments.
-    ///
-    /// The cylinder is approximated using quads for the sides and disks for the top and bottom.
+    /// Creates a capped cylinder standing upright along the Y axis.
     ///
     /// # Arguments
     ///
-    /// * `center` - The center of the cylinder.
+    /// * `center` - The center of the cylinder, halfway between its ends.
     /// * `radius` - The radius of the cylinder.
     /// * `height` - The height of the cylinder.
     /// * `mat` - A shared reference to the material applied to the cylinder.
-    /// * `segments` - The number of segments used to approximate the curved surface.
+    pub fn new(center: Point3, radius: f64, height: f64, mat: Arc<dyn Material>) -> Self {
+        Self::with_axis(center, Vec3::new(0.0, 1.0, 0.0), radius, height, mat)
+    }
+
+    /// Creates a capped cylinder oriented along `axis`.
     ///
-    /// # Returns
+    /// # Arguments
     ///
-    /// A new `Cylinder` instance.
+    /// * `center` - The center of the cylinder, halfway between its ends.
+    /// * `axis` - The direction of the cylinder, of any non-zero length.
+    /// * `radius` - The radius of the cylinder.
+    /// * `height` - The distance between the ends of the cylinder.
+    /// * `mat` - A shared reference to the material applied to the cylinder.
+    pub fn with_axis(center: Point3, axis: Vec3, radius: f64, height: f64, mat: Arc<dyn Material>) -> Self {
+        let axis = unit_vector(axis);
+        Cylinder {
+            base: center - 0.5 * height * axis,
+            axis,
+            radius,
+            height,
+            capped: true,
+            mat,
+        }
+    }
+
+    /// Creates a capped cylinder whose ends are centered on `p0` and `p1`.
     ///
     /// # Example
     ///
     /// ```
-    /// let cylinder = Cylinder::new(Point3::new(0.0, 0.0, 0.0), 1.0, 2.0, Arc::new(SomeMaterial), 16);
+    /// # use std::sync::Arc;
+    /// # use raytracer::{Color, Cylinder, Lambertian, Point3};
+    /// # let material = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3)));
+    /// // A table leg, slanted outwards
+    /// let leg = Cylinder::between(Point3::new(0.4, 0.0, 0.4), Point3::new(0.3, 1.0, 0.3), 0.05, material);
     /// ```
-    pub fn new(center: Point3, radius: f64, height: f64, mat: Arc<dyn Material>, segments: usize) -> Self {
-        let mut sides = HittableList::new();
-        let half_height = height / 2.0;
+    pub fn between(p0: Point3, p1: Point3, radius: f64, mat: Arc<dyn Material>) -> Self {
+        Self::with_axis(0.5 * (p0 + p1), p1 - p0, radius, (p1 - p0).length(), mat)
+    }
 
-        let top_center = Point3::new(center.x(), center.y() + half_height, center.z());
-        let bottom_center = Point3::new(center.x(), center.y() - half_height, center.z());
+    /// Closes the ends of the cylinder with disks, or leaves them open like a pipe.
+    pub fn capped(mut self, capped: bool) -> Self {
+        self.capped = capped;
+        self
+    }
 
-        // Add circular base disks
-        sides.add(Box::new(Disk::new(top_center, radius, mat.clone())));
-        sides.add(Box::new(Disk::new(bottom_center, radius, mat.clone())));
+    /// Builds the hit record at distance `t`, where the surface has the normal
+    /// `outward_normal` and the texture coordinates `uv`.
+    fn record(&self, r: &Ray, t: f64, outward_normal: Vec3, uv: UV) -> HitRecord {
+        let mut rec = HitRecord {
+            t,
+            p: r.at(t),
+            mat: self.mat.clone(),
+            normal: Default::default(),
+            front_face: Default::default(),
+            uv,
+        };
+        rec.set_face_normal(r, outward_normal);
+        rec
+    }
+}
 
-        // Add lateral surface quads
-        let angle_step = std::f64::consts::TAU / segments as f64;
-        for i in 0..segments {
-            let theta1 = i as f64 * angle_step;
-            let theta2 = (i + 1) as f64 * angle_step;
+impl Hittable for Cylinder {
+    /// Determines if a ray intersects the cylinder.
+    ///
+    /// The side is the set of points at distance `radius` from the axis. Removing
+    /// the components along the axis from the ray gives the 2D quadratic equation
+    /// `|O⊥ + tD⊥|² = R²`, whose roots are kept if they lie between the ends.
+    /// Each cap is a disk in the plane of its end.
+    fn hit(&self, r: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let oc = r.origin() - self.base;
+        let d = r.direction();
+        let oc_along = dot(oc, self.axis);
+        let d_along = dot(d, self.axis);
+        let oc_perp = oc - oc_along * self.axis;
+        let d_perp = d - d_along * self.axis;
 
-            let x1 = radius * theta1.cos();
-            let z1 = radius * theta1.sin();
-            let x2 = radius * theta2.cos();
-            let z2 = radius * theta2.sin();
+        let mut closest: Option<HitRecord> = None;
+        let mut t_max = t_max;
 
-            let p1 = Point3::new(center.x() + x1, bottom_center.y(), center.z() + z1);
-            let p2 = Point3::new(center.x() + x2, bottom_center.y(), center.z() + z2);
-            let p4 = Point3::new(center.x() + x1, top_center.y(), center.z() + z1);
+        // Side
+        let a = d_perp.length_squared();
+        let half_b = dot(oc_perp, d_perp);
+        let c = oc_perp.length_squared() - self.radius * self.radius;
+        let discriminant = half_b * half_b - a * c;
+        // A ray parallel to the axis never hits the side
+        if a > 0.0 && discriminant >= 0.0 {
+            let sqrt_d = f64::sqrt(discriminant);
+            for root in [(-half_b - sqrt_d) / a, (-half_b + sqrt_d) / a] {
+                if root <= t_min || t_max <= root {
+                    continue;
+                }
+                let along = oc_along + root * d_along;
+                if (0.0..=self.height).contains(&along) {
+                    let outward_normal = (oc_perp + root * d_perp) / self.radius;
+                    // Wrapped around the side, turning with the normal, and up along the axis
+                    let u = disk::polar_uv(outward_normal, self.axis, 1.0).x();
+                    let uv = UV::new(u, along / self.height);
+                    closest = Some(self.record(r, root, outward_normal, uv));
+                    t_max = root;
+                    break;
+                }
+            }
+        }
 
-            sides.add(Box::new(Quad::new(p1, p2 - p1, p4 - p1, mat.clone())));
+        // Caps
+        if self.capped && d_along != 0.0 {
+            for (along, outward_normal) in [(0.0, -self.axis), (self.height, self.axis)] {
+                let t = (along - oc_along) / d_along;
+                if t <= t_min || t_max <= t {
+                    continue;
+                }
+                let offset = oc_perp + t * d_perp;
+                if offset.length_squared() <= self.radius * self.radius {
+                    let uv = disk::polar_uv(offset, outward_normal, self.radius);
+                    closest = Some(self.record(r, t, outward_normal, uv));
+                    t_max = t;
+                }
+            }
         }
 
-        Cylinder { sides }
+        closest
     }
-}
 
-impl Hittable for Cylinder {
-    /// Checks if a given ray intersects with any of the cylinder's components (sides or bases).
-    ///
-    /// # Arguments
-    ///
-    /// * `ray` - The ray to test for intersection.
-    /// * `t_min` - The minimum valid intersection distance.
-    /// * `t_max` - The maximum valid intersection distance.
-    ///
-    /// # Returns
-    ///
-    /// An `Option<HitRecord>` containing intersection data if a hit occurs.
-    fn hit(&self, ray: &crate::ray::Ray, t_min: f64, t_max: f64) -> Option<crate::hittable::HitRecord> {
-        self.sides.hit(ray, t_min, t_max)
+    fn bounding_box(&self) -> Aabb {
+        // Each end is a disk, spreading along each axis by the radius times
+        // the sine of the angle between that axis and the cylinder's
+        let a = self.axis;
+        let extent = self.radius
+            * Vec3::new(
+                f64::sqrt(f64::max(0.0, 1.0 - a.x() * a.x())),
+                f64::sqrt(f64::max(0.0, 1.0 - a.y() * a.y())),
+                f64::sqrt(f64::max(0.0, 1.0 - a.z() * a.z())),
+            );
+        let top = self.base + self.height * self.axis;
+        Aabb::surrounding(
+            &Aabb::new(self.base - extent, self.base + extent),
+            &Aabb::new(top - extent, top + extent),
+        )
     }
 }
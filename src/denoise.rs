@@ -0,0 +1,240 @@
+//! Edge-avoiding à-trous wavelet filter, the spatial part of SVGF ("Spatiotemporal
+//! Variance-Guided Filtering", Schied et al. 2017).
+//!
+//! The image is blurred a few times with a 5x5 kernel whose taps are twice as far apart
+//! on every pass, so that a few passes reach far. Each tap is weighted down when its
+//! normal differs, or when its brightness differs more than the noise of the pixel
+//! explains, which keeps the edges of the objects and their shading sharp.
+
+use rayon::prelude::*;
+
+use crate::color::Color;
+use crate::vec3::{self, Vec3};
+
+/// Settings of [`FrameBuffer::denoise`](crate::FrameBuffer::denoise), built with
+/// chained setters or by setting the fields.
+///
+/// # Example
+///
+/// ```
+/// use raytracer::DenoiseSettings;
+///
+/// let settings = DenoiseSettings::new().iterations(4).color_sigma(2.0);
+/// assert_eq!(settings.normal_power, 128.0);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DenoiseSettings {
+    /// Number of passes: the filter reaches `2 × (2^iterations - 1)` pixels away.
+    ///
+    /// Passes whose taps are as far apart as the image is wide or high would change
+    /// nothing and are skipped, so a `width × height` image gets at most
+    /// `ceil(log2(max(width, height)))` of them.
+    pub iterations: u32,
+    /// How many standard deviations of the noise two brightnesses may differ by and
+    /// still be blended: higher values blur more.
+    pub color_sigma: f64,
+    /// How sharply the weight falls as normals diverge: higher values keep more edges.
+    pub normal_power: f64,
+}
+
+impl Default for DenoiseSettings {
+    fn default() -> Self {
+        DenoiseSettings {
+            iterations: 5,
+            color_sigma: 4.0,
+            normal_power: 128.0,
+        }
+    }
+}
+
+impl DenoiseSettings {
+    /// Creates the default settings: 5 passes, a color sigma of 4 and a normal power of 128.
+    pub fn new() -> DenoiseSettings {
+        Default::default()
+    }
+
+    /// Sets the number of passes, see the `iterations` field for how many are used.
+    pub fn iterations(mut self, iterations: u32) -> Self {
+        self.iterations = iterations;
+        self
+    }
+
+    /// Sets how many standard deviations of the noise brightnesses may differ by.
+    pub fn color_sigma(mut self, color_sigma: f64) -> Self {
+        self.color_sigma = color_sigma;
+        self
+    }
+
+    /// Sets how sharply the weight falls as normals diverge.
+    pub fn normal_power(mut self, normal_power: f64) -> Self {
+        self.normal_power = normal_power;
+        self
+    }
+}
+
+/// The weights of the B3 spline kernel, `[1, 4, 6, 4, 1] / 16`, by distance to its center.
+const KERNEL: [f64; 3] = [3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+/// Components of the albedo below this are not divided out, which would only
+/// amplify the noise of dark surfaces.
+const MIN_ALBEDO: f64 = 0.01;
+
+/// Filters the `width × height` `pixels`, guided by the albedo and normal AOVs when
+/// they are given, as 3 components per pixel.
+pub(crate) fn denoise(
+    width: usize,
+    height: usize,
+    pixels: &[Color],
+    albedo: Option<&[f64]>,
+    normal: Option<&[f64]>,
+    settings: &DenoiseSettings,
+) -> Vec<Color> {
+    let vectors = |values: &[f64]| -> Vec<Vec3> {
+        values.chunks(3).map(|v| Vec3::new(v[0], v[1], v[2])).collect()
+    };
+    let keep_dark = |x: f64| if x < MIN_ALBEDO { 1.0 } else { x };
+    let albedo: Vec<Color> = match albedo {
+        Some(albedo) => vectors(albedo)
+            .into_iter()
+            .map(|a| Color::new(keep_dark(a.x()), keep_dark(a.y()), keep_dark(a.z())))
+            .collect(),
+        None => vec![Color::new(1.0, 1.0, 1.0); pixels.len()],
+    };
+    // Normals are of unit length, or 0 on the background, but AOVs may come from elsewhere
+    let normals: Vec<Vec3> = match normal {
+        Some(normal) => vectors(normal)
+            .into_iter()
+            .map(|n| if n.near_zero() { n } else { vec3::unit_vector(n) })
+            .collect(),
+        None => vec![Vec3::default(); pixels.len()],
+    };
+
+    // The lighting alone, without the texture, which the filter must not blur
+    let mut color: Vec<Color> = pixels
+        .iter()
+        .zip(&albedo)
+        .map(|(&c, &a)| Color::new(c.x() / a.x(), c.y() / a.y(), c.z() / a.z()))
+        .collect();
+    let mut variance = spatial_variance(width, height, &color);
+
+    // Past the size of the image, every tap but the center one falls outside of it
+    let steps = (0..settings.iterations)
+        .map_while(|iteration| 1usize.checked_shl(iteration))
+        .take_while(|&step| step < width.max(height));
+    for step in steps {
+        let pass = Pass {
+            width,
+            height,
+            step,
+            color: &color,
+            variance: &variance,
+            normals: &normals,
+            settings,
+        };
+        (color, variance) = (0..width * height).into_par_iter().map(|p| pass.filter(p)).unzip();
+    }
+
+    color.iter().zip(&albedo).map(|(&c, &a)| c * a).collect()
+}
+
+/// One pass of the filter over the whole image.
+struct Pass<'a> {
+    width: usize,
+    height: usize,
+    /// Distance between the taps, in pixels.
+    step: usize,
+    color: &'a [Color],
+    variance: &'a [f64],
+    normals: &'a [Vec3],
+    settings: &'a DenoiseSettings,
+}
+
+impl Pass<'_> {
+    /// Filters the pixel `p`, returning its color and the variance of that average.
+    fn filter(&self, p: usize) -> (Color, f64) {
+        let (x, y) = ((p % self.width) as isize, (p / self.width) as isize);
+        let luminance_p = luminance(self.color[p]);
+        // The noise of the pixel, smoothed so that a single outlier does not decide it
+        let sigma = self.settings.color_sigma * self.blurred_variance(x, y).sqrt() + 1e-10;
+
+        let mut sum = Color::new(0.0, 0.0, 0.0);
+        let mut sum_weights = 0.0;
+        let mut sum_variance = 0.0;
+        for dy in -2isize..=2 {
+            for dx in -2isize..=2 {
+                let Some(q) = self.index(x + dx * self.step as isize, y + dy * self.step as isize) else {
+                    continue;
+                };
+                let weight = KERNEL[dx.unsigned_abs()]
+                    * KERNEL[dy.unsigned_abs()]
+                    * normal_weight(self.normals[p], self.normals[q], self.settings.normal_power)
+                    * f64::exp(-(luminance_p - luminance(self.color[q])).abs() / sigma);
+                sum += weight * self.color[q];
+                sum_weights += weight;
+                // Variances add up with the squares of the weights
+                sum_variance += weight * weight * self.variance[q];
+            }
+        }
+        // The pixel itself always has a weight, sum_weights is never 0
+        (sum / sum_weights, sum_variance / (sum_weights * sum_weights))
+    }
+
+    /// The variance at `(x, y)` averaged over its 3x3 neighbors with a Gaussian kernel.
+    fn blurred_variance(&self, x: isize, y: isize) -> f64 {
+        const GAUSSIAN: [f64; 2] = [1.0 / 2.0, 1.0 / 4.0];
+        let (mut sum, mut sum_weights) = (0.0, 0.0);
+        for dy in -1isize..=1 {
+            for dx in -1isize..=1 {
+                if let Some(q) = self.index(x + dx, y + dy) {
+                    let weight = GAUSSIAN[dx.unsigned_abs()] * GAUSSIAN[dy.unsigned_abs()];
+                    sum += weight * self.variance[q];
+                    sum_weights += weight;
+                }
+            }
+        }
+        sum / sum_weights
+    }
+
+    /// The index of the pixel at `(x, y)`, `None` outside of the image.
+    fn index(&self, x: isize, y: isize) -> Option<usize> {
+        let inside = x >= 0 && y >= 0 && x < self.width as isize && y < self.height as isize;
+        inside.then(|| y as usize * self.width + x as usize)
+    }
+}
+
+/// How much the taps of normals `a` and `b` are blended, from 0 to 1. Pixels without
+/// normals, on the background, are only blended together.
+fn normal_weight(a: Vec3, b: Vec3, power: f64) -> f64 {
+    match (a.near_zero(), b.near_zero()) {
+        (true, true) => 1.0,
+        (false, false) => f64::max(0.0, vec3::dot(a, b)).powf(power),
+        _ => 0.0,
+    }
+}
+
+/// The variance of the luminance over the 5x5 pixels around every pixel, the first
+/// estimate of its noise.
+fn spatial_variance(width: usize, height: usize, color: &[Color]) -> Vec<f64> {
+    (0..width * height)
+        .into_par_iter()
+        .map(|p| {
+            let (x, y) = (p % width, p / width);
+            let (mut sum, mut sum_squares, mut count) = (0.0, 0.0, 0.0);
+            for qy in y.saturating_sub(2)..(y + 3).min(height) {
+                for qx in x.saturating_sub(2)..(x + 3).min(width) {
+                    let l = luminance(color[qy * width + qx]);
+                    sum += l;
+                    sum_squares += l * l;
+                    count += 1.0;
+                }
+            }
+            let mean = sum / count;
+            f64::max(0.0, sum_squares / count - mean * mean)
+        })
+        .collect()
+}
+
+/// The brightness of `color` as perceived, with the Rec. 709 weights.
+fn luminance(color: Color) -> f64 {
+    0.2126 * color.x() + 0.7152 * color.y() + 0.0722 * color.z()
+}
@@ -1,21 +1,24 @@
 use crate::vec3::*;
 use crate::material::*;
 use crate::hittable::{HitRecord, Hittable};
+use crate::aabb::Aabb;
+use crate::common::{self, PI};
+use crate::ray::Ray;
+use crate::vec2::UV;
 use std::sync::Arc;
 
 /// A disk-shaped hittable object in 3D space.
 /// The disk is defined by its center, radius, and a material.
-/// By default, the normal is set to point upwards along the Y-axis.
+/// By default, the normal is set to point upwards along the Y-axis,
+/// [`Disk::with_normal`] orients the disk in any direction.
 ///
 /// # Example
 ///
 /// ```
 /// use std::sync::Arc;
-/// use crate::vec3::Point3;
-/// use crate::material::Lambertian;
-/// use crate::disk::Disk;
+/// use raytracer::{Color, Disk, Lambertian, Point3};
 ///
-/// let material = Arc::new(Lambertian::new(crate::vec3::Color::new(0.8, 0.3, 0.3)));
+/// let material = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3)));
 /// let disk = Disk::new(Point3::new(0.0, 0.0, 0.0), 1.0, material);
 /// ```
 pub struct Disk {
@@ -45,18 +48,55 @@ impl Disk {
     /// # Example
     ///
     /// ```
-    /// let disk = Disk::new(Point3::new(0.0, 0.0, 0.0), 1.0, Arc::new(SomeMaterial));
+    /// # use std::sync::Arc;
+    /// # use raytracer::{Color, Disk, Lambertian, Point3};
+    /// # let material = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3)));
+    /// let disk = Disk::new(Point3::new(0.0, 0.0, 0.0), 1.0, material);
     /// ```
     pub fn new(center: Point3, radius: f64, mat: Arc<dyn Material>) -> Self {
+        Self::with_normal(center, Vec3::new(0.0, 1.0, 0.0), radius, mat) // Default normal pointing up
+    }
+
+    /// Creates a new disk facing the direction `normal`.
+    ///
+    /// # Arguments
+    ///
+    /// * `center` - The center of the disk.
+    /// * `normal` - The direction the front face of the disk faces, of any non-zero length.
+    /// * `radius` - The radius of the disk.
+    /// * `mat` - A shared reference to the material applied to the disk.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use raytracer::{Color, Disk, Lambertian, Point3, Vec3};
+    /// # let material = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3)));
+    /// // A wheel, standing up and facing the X axis
+    /// let wheel = Disk::with_normal(Point3::new(0.0, 0.5, 0.0), Vec3::new(1.0, 0.0, 0.0), 0.5, material);
+    /// ```
+    pub fn with_normal(center: Point3, normal: Vec3, radius: f64, mat: Arc<dyn Material>) -> Self {
         Self {
             center,
-            normal: Vec3::new(0.0, 1.0, 0.0), // Default normal pointing up
+            normal: unit_vector(normal),
             radius,
             mat,
         }
     }
 }
 
+/// Returns the polar coordinates of `offset`, a vector in the plane of normal `normal`,
+/// as texture coordinates: `u` is the angle around the normal, from 0 to 1 in a full
+/// turn, and `v` the length of `offset` over `radius`.
+///
+/// Also used by the caps of [`Cylinder`](crate::Cylinder)s.
+pub(crate) fn polar_uv(offset: Vec3, normal: Vec3, radius: f64) -> UV {
+    let (a, b) = orthonormal_basis(normal);
+    let phi = f64::atan2(dot(offset, b), dot(offset, a));
+    // atan2 is in [-π, π], the turn starts along `a`
+    UV::new(phi.rem_euclid(2.0 * PI) / (2.0 * PI), offset.length() / radius)
+}
+
 impl Hittable for Disk {
     /// Checks if a given ray intersects with the disk.
     ///
@@ -69,7 +109,7 @@ impl Hittable for Disk {
     /// # Returns
     ///
     /// An `Option<HitRecord>` containing intersection data if a hit occurs.
-    fn hit(&self, ray: &crate::ray::Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
         let denom = dot(self.normal, ray.direction());
 
         // Check if the ray is parallel to the disk
@@ -95,9 +135,46 @@ impl Hittable for Disk {
             mat: self.mat.clone(),
             normal: self.normal,
             front_face: true,
+            uv: polar_uv(p - self.center, self.normal, self.radius),
         };
         rec.set_face_normal(ray, self.normal);
 
         Some(rec)
     }
+
+    fn bounding_box(&self) -> Aabb {
+        // Along each axis the disk spreads by the radius times the sine of
+        // the angle between that axis and the normal
+        let n = self.normal;
+        let extent = self.radius
+            * Vec3::new(
+                f64::sqrt(f64::max(0.0, 1.0 - n.x() * n.x())),
+                f64::sqrt(f64::max(0.0, 1.0 - n.y() * n.y())),
+                f64::sqrt(f64::max(0.0, 1.0 - n.z() * n.z())),
+            );
+        Aabb::new(self.center - extent, self.center + extent)
+    }
+
+    /// Returns the density of the directions towards the disk picked by [`Hittable::random`].
+    ///
+    /// Points are picked uniformly over the area of the disk, a density of `1 / area`,
+    /// which becomes `distance² / (cos θ × area)` per unit of solid angle seen from `origin`.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let Some(rec) = self.hit(&Ray::new(origin, direction), 0.001, common::INFINITY) else {
+            return 0.0;
+        };
+        let distance_squared = rec.t * rec.t * direction.length_squared();
+        let cosine = f64::abs(dot(direction, self.normal)) / direction.length();
+        distance_squared / (cosine * PI * self.radius * self.radius)
+    }
+
+    /// Returns the direction from `origin` to a random point of the disk.
+    fn random(&self, origin: Point3) -> Vec3 {
+        let (u, v) = orthonormal_basis(self.normal);
+        // The square root spreads the points evenly rather than bunching them at the center
+        let r = self.radius * f64::sqrt(common::random_double());
+        let phi = 2.0 * PI * common::random_double();
+        let p = self.center + r * f64::cos(phi) * u + r * f64::sin(phi) * v;
+        p - origin
+    }
 }
@@ -0,0 +1,194 @@
+//! Writer of OpenEXR images: single part, scanlines of 32-bit float channels.
+//!
+//! Only what the renderer produces is supported, see the
+//! [file layout](https://openexr.com/en/latest/OpenEXRFileLayout.html) for the full format.
+
+mod piz;
+
+use std::io::{self, Write};
+
+use rayon::prelude::*;
+
+/// Magic number of OpenEXR files.
+const MAGIC: [u8; 4] = [0x76, 0x2f, 0x31, 0x01];
+/// Version 2, single part scanline image with short names.
+const VERSION: [u8; 4] = [2, 0, 0, 0];
+/// Pixel type of 32-bit float channels.
+const FLOAT: i32 = 2;
+
+/// How the pixels of an OpenEXR image are compressed. Every mode is lossless.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ExrCompression {
+    /// No compression: the largest files, the fastest to write and read.
+    None,
+    /// Deflate of blocks of 16 scanlines, best on smooth images.
+    #[default]
+    Zip,
+    /// Wavelet and Huffman coding of blocks of 32 scanlines, better than ZIP on grainy images.
+    Piz,
+}
+
+impl ExrCompression {
+    /// Value of the `compression` attribute.
+    fn id(self) -> u8 {
+        match self {
+            ExrCompression::None => 0,
+            ExrCompression::Zip => 3,
+            ExrCompression::Piz => 4,
+        }
+    }
+
+    /// Number of scanlines compressed together.
+    fn lines_per_block(self) -> usize {
+        match self {
+            ExrCompression::None => 1,
+            ExrCompression::Zip => 16,
+            ExrCompression::Piz => 32,
+        }
+    }
+}
+
+/// A channel of an image: one float per pixel, row by row from the top.
+pub(crate) struct Channel {
+    pub name: String,
+    pub samples: Vec<f32>,
+}
+
+impl Channel {
+    pub fn new(name: impl Into<String>, samples: Vec<f32>) -> Channel {
+        Channel {
+            name: name.into(),
+            samples,
+        }
+    }
+}
+
+/// Writes the image of `width × height` pixels made of `channels`.
+///
+/// The channels may be given in any order, they are stored sorted by name as the
+/// format requires. Readers recognize `R`, `G`, `B` and `A`, and layers as prefixes
+/// ending with a dot, like `normal.X`.
+pub(crate) fn write_exr(
+    out: &mut impl Write,
+    width: usize,
+    height: usize,
+    mut channels: Vec<Channel>,
+    compression: ExrCompression,
+) -> io::Result<()> {
+    channels.sort_by(|a, b| a.name.cmp(&b.name));
+    for channel in &channels {
+        assert_eq!(channel.samples.len(), width * height, "channel size does not match the image");
+    }
+
+    let header = header(width, height, &channels, compression);
+    let lines = compression.lines_per_block();
+    let blocks: Vec<(usize, Vec<u8>)> = (0..height)
+        .step_by(lines)
+        .collect::<Vec<_>>()
+        .into_par_iter()
+        .map(|y| {
+            let ny = lines.min(height - y);
+            let raw = block(&channels, width, y, ny);
+            let compressed = match compression {
+                ExrCompression::None => None,
+                ExrCompression::Zip => Some(zip(&raw)),
+                ExrCompression::Piz => Some(piz::compress(&raw, width, ny, channels.len())),
+            };
+            // Readers take a block as stored when it is not smaller than the pixels
+            match compressed {
+                Some(data) if data.len() < raw.len() => (y, data),
+                _ => (y, raw),
+            }
+        })
+        .collect();
+
+    out.write_all(&header)?;
+    // Offset table: where each block starts, counted from the beginning of the file
+    let mut offset = (header.len() + 8 * blocks.len()) as u64;
+    for (_, data) in &blocks {
+        out.write_all(&offset.to_le_bytes())?;
+        offset += 8 + data.len() as u64;
+    }
+    for (y, data) in &blocks {
+        out.write_all(&(*y as i32).to_le_bytes())?;
+        out.write_all(&(data.len() as i32).to_le_bytes())?;
+        out.write_all(data)?;
+    }
+    Ok(())
+}
+
+/// The magic number, the version and the attributes of the image.
+fn header(width: usize, height: usize, channels: &[Channel], compression: ExrCompression) -> Vec<u8> {
+    let mut header = Vec::new();
+    header.extend_from_slice(&MAGIC);
+    header.extend_from_slice(&VERSION);
+
+    let mut list = Vec::new();
+    for channel in channels {
+        list.extend_from_slice(channel.name.as_bytes());
+        list.push(0);
+        list.extend_from_slice(&FLOAT.to_le_bytes());
+        // pLinear and three reserved bytes, then the sampling in x and y
+        list.extend_from_slice(&[0, 0, 0, 0]);
+        list.extend_from_slice(&1i32.to_le_bytes());
+        list.extend_from_slice(&1i32.to_le_bytes());
+    }
+    list.push(0);
+    attribute(&mut header, "channels", "chlist", &list);
+    attribute(&mut header, "compression", "compression", &[compression.id()]);
+
+    let mut window = Vec::new();
+    for value in [0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend_from_slice(&value.to_le_bytes());
+    }
+    attribute(&mut header, "dataWindow", "box2i", &window);
+    attribute(&mut header, "displayWindow", "box2i", &window);
+    // Increasing y, the top row first
+    attribute(&mut header, "lineOrder", "lineOrder", &[0]);
+    attribute(&mut header, "pixelAspectRatio", "float", &1f32.to_le_bytes());
+    attribute(&mut header, "screenWindowCenter", "v2f", &[0; 8]);
+    attribute(&mut header, "screenWindowWidth", "float", &1f32.to_le_bytes());
+    header.push(0);
+    header
+}
+
+fn attribute(header: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    header.extend_from_slice(name.as_bytes());
+    header.push(0);
+    header.extend_from_slice(kind.as_bytes());
+    header.push(0);
+    header.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    header.extend_from_slice(value);
+}
+
+/// The uncompressed pixels of the `ny` rows starting at `y`: each row holds every
+/// channel in turn, in the order of the header.
+fn block(channels: &[Channel], width: usize, y: usize, ny: usize) -> Vec<u8> {
+    let mut raw = Vec::with_capacity(ny * width * channels.len() * 4);
+    for row in y..y + ny {
+        for channel in channels {
+            for sample in &channel.samples[row * width..(row + 1) * width] {
+                raw.extend_from_slice(&sample.to_le_bytes());
+            }
+        }
+    }
+    raw
+}
+
+/// ZIP compression: the bytes are split into the even and odd ones and replaced by the
+/// difference with the previous byte before being deflated, which makes floats that
+/// vary slowly much more compressible.
+fn zip(raw: &[u8]) -> Vec<u8> {
+    let half = raw.len().div_ceil(2);
+    let mut reordered = vec![0u8; raw.len()];
+    for (i, &byte) in raw.iter().enumerate() {
+        reordered[if i % 2 == 0 { i / 2 } else { half + i / 2 }] = byte;
+    }
+    let mut previous = reordered.first().copied().unwrap_or_default();
+    for byte in reordered.iter_mut().skip(1) {
+        let current = *byte;
+        *byte = current.wrapping_sub(previous).wrapping_add(128);
+        previous = current;
+    }
+    miniz_oxide::deflate::compress_to_vec_zlib(&reordered, 6)
+}
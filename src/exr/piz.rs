@@ -0,0 +1,365 @@
+//! PIZ compression of OpenEXR, as done by the reference implementation: the 16-bit
+//! words of the pixels are renumbered to the values actually used, transformed by a
+//! Haar wavelet, then Huffman coded.
+
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+
+/// Number of 16-bit words in a sample of a 32-bit float channel.
+const WORDS_PER_SAMPLE: usize = 2;
+const USHORT_RANGE: usize = 1 << 16;
+const BITMAP_SIZE: usize = USHORT_RANGE >> 3;
+
+/// Compresses the `ny` rows of `nx` pixels in `raw`, laid out as in the file: each row
+/// holds `channels` float channels in turn.
+pub(super) fn compress(raw: &[u8], nx: usize, ny: usize, channels: usize) -> Vec<u8> {
+    // Gather the words of each channel, the wavelet works on one channel at a time
+    let row_words = nx * WORDS_PER_SAMPLE;
+    let channel_words = row_words * ny;
+    let mut words = vec![0u16; channel_words * channels];
+    for (i, word) in raw.chunks_exact(2).enumerate() {
+        let (row, channel, x) = (i / (row_words * channels), i / row_words % channels, i % row_words);
+        words[channel * channel_words + row * row_words + x] = u16::from_le_bytes([word[0], word[1]]);
+    }
+
+    // Renumber the words to the values present, so that the wavelet has fewer bits to handle
+    let mut bitmap = vec![0u8; BITMAP_SIZE];
+    for &word in &words {
+        bitmap[word as usize >> 3] |= 1 << (word & 7);
+    }
+    // Zero is always assumed present and is not stored
+    bitmap[0] &= !1;
+    let used = bitmap.iter().position(|&b| b != 0).map(|min| {
+        let max = bitmap.iter().rposition(|&b| b != 0).unwrap_or(min);
+        (min, max)
+    });
+
+    let mut lut = vec![0u16; USHORT_RANGE];
+    let mut count = 0u16;
+    for (value, entry) in lut.iter_mut().enumerate() {
+        if value == 0 || bitmap[value >> 3] & (1 << (value & 7)) != 0 {
+            *entry = count;
+            count = count.wrapping_add(1);
+        }
+    }
+    let max_value = count.wrapping_sub(1);
+    for word in words.iter_mut() {
+        *word = lut[*word as usize];
+    }
+
+    for channel in words.chunks_exact_mut(channel_words) {
+        for start in 0..WORDS_PER_SAMPLE {
+            wav2_encode(channel, start, nx, WORDS_PER_SAMPLE, ny, row_words, max_value);
+        }
+    }
+
+    let mut out = Vec::new();
+    match used {
+        Some((min, max)) => {
+            out.extend_from_slice(&(min as u16).to_le_bytes());
+            out.extend_from_slice(&(max as u16).to_le_bytes());
+            out.extend_from_slice(&bitmap[min..=max]);
+        }
+        None => {
+            // An empty range: the minimum after the maximum
+            out.extend_from_slice(&(BITMAP_SIZE as u16 - 1).to_le_bytes());
+            out.extend_from_slice(&0u16.to_le_bytes());
+        }
+    }
+    let huffman = huf_compress(&words);
+    out.extend_from_slice(&(huffman.len() as i32).to_le_bytes());
+    out.extend_from_slice(&huffman);
+    out
+}
+
+/// 2D Haar wavelet transform, in place, of the `nx × ny` words of `buf` starting at
+/// `start`, `ox` words apart in a row and `oy` words apart in a column.
+///
+/// Words below `max` fit in 14 bits, which allows an exact transform without modular
+/// arithmetic.
+fn wav2_encode(buf: &mut [u16], start: usize, nx: usize, ox: usize, ny: usize, oy: usize, max: u16) {
+    let encode = if max < (1 << 14) { wenc14 } else { wenc16 };
+    let n = nx.min(ny);
+    let mut p = 1;
+    let mut p2 = 2;
+
+    while p2 <= n {
+        let (ox1, ox2) = (ox * p, ox * p2);
+        let (oy1, oy2) = (oy * p, oy * p2);
+        let ey = start + oy * (ny - p2);
+
+        let mut py = start;
+        while py <= ey {
+            let ex = py + ox * (nx - p2);
+            let mut px = py;
+            while px <= ex {
+                let (p01, p10) = (px + ox1, px + oy1);
+                let p11 = p10 + ox1;
+                let (i00, i01) = encode(buf[px], buf[p01]);
+                let (i10, i11) = encode(buf[p10], buf[p11]);
+                (buf[px], buf[p10]) = encode(i00, i10);
+                (buf[p01], buf[p11]) = encode(i01, i11);
+                px += ox2;
+            }
+            // Odd column
+            if nx & p != 0 {
+                let p10 = px + oy1;
+                (buf[px], buf[p10]) = encode(buf[px], buf[p10]);
+            }
+            py += oy2;
+        }
+        // Odd row
+        if ny & p != 0 {
+            let ex = py + ox * (nx - p2);
+            let mut px = py;
+            while px <= ex {
+                let p01 = px + ox1;
+                (buf[px], buf[p01]) = encode(buf[px], buf[p01]);
+                px += ox2;
+            }
+        }
+
+        p = p2;
+        p2 <<= 1;
+    }
+}
+
+/// Average and difference of two 14-bit words.
+fn wenc14(a: u16, b: u16) -> (u16, u16) {
+    let (a, b) = (a as i16 as i32, b as i16 as i32);
+    (((a + b) >> 1) as u16, (a - b) as u16)
+}
+
+/// Average and difference of two 16-bit words, modulo 2^16.
+fn wenc16(a: u16, b: u16) -> (u16, u16) {
+    const OFFSET: i32 = 1 << 15;
+    const MASK: i32 = 0xffff;
+    let ao = (a as i32 + OFFSET) & MASK;
+    let mut m = (ao + b as i32) >> 1;
+    let d = ao - b as i32;
+    if d < 0 {
+        m = (m + OFFSET) & MASK;
+    }
+    (m as u16, (d & MASK) as u16)
+}
+
+/// Number of symbols: every 16-bit value and the run-length symbol after the largest.
+const HUF_ENCSIZE: usize = USHORT_RANGE + 1;
+/// Longest code, so that a code and a partial byte fit in 64 bits.
+const MAX_CODE_LENGTH: usize = 58;
+/// In the code table, lengths from 59 to 62 stand for runs of 2 to 5 unused symbols...
+const SHORT_ZEROCODE_RUN: u64 = 59;
+/// ...and 63 for a longer run, whose length follows on 8 bits.
+const LONG_ZEROCODE_RUN: u64 = 63;
+const SHORTEST_LONG_RUN: usize = 2 + (LONG_ZEROCODE_RUN - SHORT_ZEROCODE_RUN) as usize;
+const LONGEST_LONG_RUN: usize = 255 + SHORTEST_LONG_RUN;
+
+/// Huffman codes, as `code << 6 | length` for each symbol and 0 for unused ones.
+type CodeTable = Vec<u64>;
+
+fn code_length(code: u64) -> u32 {
+    (code & 63) as u32
+}
+
+/// Huffman codes `data`, preceded by the range of symbols and the table of code lengths.
+fn huf_compress(data: &[u16]) -> Vec<u8> {
+    if data.is_empty() {
+        return Vec::new();
+    }
+    let mut frequencies = vec![0u64; HUF_ENCSIZE];
+    for &symbol in data {
+        frequencies[symbol as usize] += 1;
+    }
+    let (min, rlc, codes) = build_codes(&mut frequencies);
+
+    let mut table = BitWriter::default();
+    pack_table(&codes, min, rlc, &mut table);
+    let table = table.finish();
+
+    let mut encoded = BitWriter::default();
+    encode(&codes, data, rlc, &mut encoded);
+    let bits = encoded.bit_count();
+    let encoded = encoded.finish();
+
+    let mut out = Vec::with_capacity(20 + table.len() + encoded.len());
+    for value in [min as u32, rlc as u32, table.len() as u32, bits as u32, 0] {
+        out.extend_from_slice(&value.to_le_bytes());
+    }
+    out.extend_from_slice(&table);
+    out.extend_from_slice(&encoded);
+    out
+}
+
+/// Builds canonical Huffman codes for the symbols of non-zero frequency, plus a
+/// run-length symbol right after the largest one.
+///
+/// Returns the smallest symbol, the run-length symbol and the codes.
+fn build_codes(frequencies: &mut [u64]) -> (usize, usize, CodeTable) {
+    let min = frequencies.iter().position(|&f| f != 0).unwrap_or(0);
+    let max = frequencies.iter().rposition(|&f| f != 0).unwrap_or(min);
+    let rlc = max + 1;
+    frequencies[rlc] = 1;
+
+    // Each merge of two subtrees makes the codes of all their symbols one bit longer.
+    // The symbols of a subtree are kept in a list threaded through `next`, ending on
+    // a symbol pointing to itself.
+    let mut next: Vec<usize> = (0..HUF_ENCSIZE).collect();
+    let mut lengths = vec![0u64; HUF_ENCSIZE];
+    let mut heap: BinaryHeap<Reverse<(u64, usize)>> = (min..=rlc)
+        .filter(|&symbol| frequencies[symbol] != 0)
+        .map(|symbol| Reverse((frequencies[symbol], symbol)))
+        .collect();
+
+    while heap.len() > 1 {
+        let Reverse((fmm, mm)) = heap.pop().unwrap();
+        let Reverse((fm, m)) = heap.pop().unwrap();
+        heap.push(Reverse((fm + fmm, m)));
+
+        let mut j = m;
+        loop {
+            lengths[j] += 1;
+            if next[j] == j {
+                next[j] = mm;
+                break;
+            }
+            j = next[j];
+        }
+        let mut j = mm;
+        loop {
+            lengths[j] += 1;
+            if next[j] == j {
+                break;
+            }
+            j = next[j];
+        }
+    }
+
+    canonical_codes(&mut lengths);
+    (min, rlc, lengths)
+}
+
+/// Replaces the code lengths by canonical codes of these lengths, the longest codes
+/// being numerically the smallest.
+fn canonical_codes(lengths: &mut [u64]) {
+    let mut count = [0u64; MAX_CODE_LENGTH + 1];
+    for &length in lengths.iter() {
+        count[length as usize] += 1;
+    }
+    // First code of each length
+    let mut code = 0;
+    for length in (1..=MAX_CODE_LENGTH).rev() {
+        let next = (code + count[length]) >> 1;
+        count[length] = code;
+        code = next;
+    }
+    for entry in lengths.iter_mut() {
+        let length = *entry as usize;
+        if length > 0 {
+            *entry = length as u64 | count[length] << 6;
+            count[length] += 1;
+        }
+    }
+}
+
+/// Writes the code lengths of the symbols from `min` to `max`, 6 bits each, runs of
+/// unused symbols being shortened.
+fn pack_table(codes: &CodeTable, min: usize, max: usize, out: &mut BitWriter) {
+    let mut symbol = min;
+    while symbol <= max {
+        let length = code_length(codes[symbol]);
+        if length == 0 {
+            let mut run = 1;
+            while symbol < max && run < LONGEST_LONG_RUN && code_length(codes[symbol + 1]) == 0 {
+                symbol += 1;
+                run += 1;
+            }
+            if run >= SHORTEST_LONG_RUN {
+                out.write(6, LONG_ZEROCODE_RUN);
+                out.write(8, (run - SHORTEST_LONG_RUN) as u64);
+                symbol += 1;
+                continue;
+            }
+            if run >= 2 {
+                out.write(6, SHORT_ZEROCODE_RUN + run as u64 - 2);
+                symbol += 1;
+                continue;
+            }
+        }
+        out.write(6, length as u64);
+        symbol += 1;
+    }
+}
+
+/// Writes the codes of `data`, runs of up to 256 equal symbols being written as the
+/// symbol, the run-length symbol `rlc` and the number of repetitions when shorter.
+fn encode(codes: &CodeTable, data: &[u16], rlc: usize, out: &mut BitWriter) {
+    let run_code = codes[rlc];
+    let mut send = |symbol: u16, repeats: u64| {
+        let code = codes[symbol as usize];
+        let length = code_length(code) as u64;
+        if length + code_length(run_code) as u64 + 8 < length * repeats {
+            out.write_code(code);
+            out.write_code(run_code);
+            out.write(8, repeats);
+        } else {
+            for _ in 0..=repeats {
+                out.write_code(code);
+            }
+        }
+    };
+
+    let mut symbol = data[0];
+    let mut repeats = 0;
+    for &next in &data[1..] {
+        if next == symbol && repeats < 255 {
+            repeats += 1;
+        } else {
+            send(symbol, repeats);
+            repeats = 0;
+        }
+        symbol = next;
+    }
+    send(symbol, repeats);
+}
+
+/// Packs bits most significant first.
+#[derive(Default)]
+struct BitWriter {
+    bytes: Vec<u8>,
+    /// Bits not written yet, in the lowest `pending` bits.
+    buffer: u64,
+    pending: u32,
+}
+
+impl BitWriter {
+    fn write(&mut self, count: u32, bits: u64) {
+        if count > 32 {
+            // The buffer holds up to 7 pending bits, more would overflow it
+            self.write(count - 32, bits >> 32);
+            self.write(32, bits & 0xffff_ffff);
+            return;
+        }
+        self.buffer = self.buffer << count | bits;
+        self.pending += count;
+        while self.pending >= 8 {
+            self.pending -= 8;
+            self.bytes.push((self.buffer >> self.pending) as u8);
+        }
+    }
+
+    fn write_code(&mut self, code: u64) {
+        self.write(code_length(code), code >> 6);
+    }
+
+    fn bit_count(&self) -> usize {
+        self.bytes.len() * 8 + self.pending as usize
+    }
+
+    /// Returns the bytes, the last one padded with zeros.
+    fn finish(mut self) -> Vec<u8> {
+        if self.pending > 0 {
+            self.bytes.push((self.buffer << (8 - self.pending)) as u8);
+        }
+        self.bytes
+    }
+}
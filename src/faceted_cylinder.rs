@@ -0,0 +1,131 @@
+use crate::vec3::*;
+use crate::material::*;
+use std::sync::Arc;
+use crate::hittable::Hittable;
+use crate::aabb::Aabb;
+use crate::hittable_list::HittableList;
+use crate::quad::Quad;
+use crate::disk::Disk;
+
+/// A cylinder shape composed of multiple quads and two disks.
+/// The cylinder is centered around a given point, with a specified radius, height, and material.
+///
+/// Its silhouette is a polygon and each quad is flat shaded, for a low-poly look.
+/// See [`Cylinder`](crate::Cylinder) for a smooth one.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use raytracer::{Color, FacetedCylinder, Lambertian, Point3};
+///
+/// let material = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3)));
+/// let cylinder = FacetedCylinder::new(Point3::new(0.0, 0.0, 0.0), 1.0, 2.0, material, 16);
+/// ```
+pub struct FacetedCylinder {
+    /// The list of hittable objects that make up the cylinder (quads for the sides and disks for the top and bottom).
+    pub sides: HittableList,
+}
+
+impl FacetedCylinder {
+    /// Creates a new cylinder with a given center, radius, height, material, and number of segments.
+    ///
+    /// The cylinder is approximated using quads for the sides and disks for the top and bottom.
+    ///
+    /// # Arguments
+    ///
+    /// * `center` - The center of the cylinder.
+    /// * `radius` - The radius of the cylinder.
+    /// * `height` - The height of the cylinder.
+    /// * `mat` - A shared reference to the material applied to the cylinder.
+    /// * `segments` - The number of segments used to approximate the curved surface.
+    ///
+    /// # Returns
+    ///
+    /// A new `FacetedCylinder` instance.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use raytracer::{Color, FacetedCylinder, Lambertian, Point3};
+    /// # let material = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3)));
+    /// let cylinder = FacetedCylinder::new(Point3::new(0.0, 0.0, 0.0), 1.0, 2.0, material, 16);
+    /// ```
+    pub fn new(center: Point3, radius: f64, height: f64, mat: Arc<dyn Material>, segments: usize) -> Self {
+        let half_height = Vec3::new(0.0, height / 2.0, 0.0);
+        Self::between(center - half_height, center + half_height, radius, mat, segments)
+    }
+
+    /// Creates a new cylinder whose ends are centered on `p0` and `p1`.
+    ///
+    /// # Arguments
+    ///
+    /// * `p0`, `p1` - The centers of the two ends of the cylinder.
+    /// * `radius` - The radius of the cylinder.
+    /// * `mat` - A shared reference to the material applied to the cylinder.
+    /// * `segments` - The number of segments used to approximate the curved surface.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use raytracer::{Color, FacetedCylinder, Lambertian, Point3};
+    /// # let material = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3)));
+    /// // A hexagonal pencil lying along the X axis
+    /// let pencil = FacetedCylinder::between(
+    ///     Point3::new(-1.0, 0.1, 0.0),
+    ///     Point3::new(1.0, 0.1, 0.0),
+    ///     0.1,
+    ///     material,
+    ///     6,
+    /// );
+    /// ```
+    pub fn between(p0: Point3, p1: Point3, radius: f64, mat: Arc<dyn Material>, segments: usize) -> Self {
+        let mut sides = HittableList::new();
+        let axis = unit_vector(p1 - p0);
+        let (u, v) = orthonormal_basis(axis);
+
+        // Add circular base disks, facing outwards
+        sides.add(Box::new(Disk::with_normal(p1, axis, radius, mat.clone())));
+        sides.add(Box::new(Disk::with_normal(p0, -axis, radius, mat.clone())));
+
+        // Add lateral surface quads
+        let angle_step = std::f64::consts::TAU / segments as f64;
+        let around = |theta: f64| radius * (theta.cos() * u + theta.sin() * v);
+        for i in 0..segments {
+            let offset1 = around(i as f64 * angle_step);
+            let offset2 = around((i + 1) as f64 * angle_step);
+
+            let bottom = p0 + offset1;
+            sides.add(Box::new(Quad::new(bottom, (p0 + offset2) - bottom, p1 - p0, mat.clone())));
+        }
+
+        FacetedCylinder { sides }
+    }
+}
+
+impl Hittable for FacetedCylinder {
+    /// Checks if a given ray intersects with any of the cylinder's components (sides or bases).
+    ///
+    /// # Arguments
+    ///
+    /// * `ray` - The ray to test for intersection.
+    /// * `t_min` - The minimum valid intersection distance.
+    /// * `t_max` - The maximum valid intersection distance.
+    ///
+    /// # Returns
+    ///
+    /// An `Option<HitRecord>` containing intersection data if a hit occurs.
+    fn hit(&self, ray: &crate::ray::Ray, t_min: f64, t_max: f64) -> Option<crate::hittable::HitRecord> {
+        // Skip the faces altogether when the ray misses the cylinder
+        if !self.sides.bounding_box().hit(ray, t_min, t_max) {
+            return None;
+        }
+        self.sides.hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.sides.bounding_box()
+    }
+}
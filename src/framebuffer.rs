@@ -0,0 +1,438 @@
+use std::io::{self, Write};
+use std::path::Path;
+
+use crate::aov::Aov;
+use crate::color::Color;
+use crate::denoise::{self, DenoiseSettings};
+use crate::exr::{self, Channel, ExrCompression};
+use crate::tonemap::DisplayTransform;
+
+/// A file format a [`FrameBuffer`] can be written in.
+///
+/// ```
+/// use raytracer::ImageFormat;
+///
+/// assert_eq!(ImageFormat::from_path("render.png"), Some(ImageFormat::Png));
+/// assert_eq!(ImageFormat::from_path("render.PPM"), Some(ImageFormat::Ppm));
+/// assert_eq!(ImageFormat::from_path("render.bmp"), None);
+/// assert!(matches!(ImageFormat::from_path("render.exr"), Some(ImageFormat::Exr { alpha: false, .. })));
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    /// Binary (P6) PPM, the format of `.ppm` files.
+    Ppm,
+    /// ASCII (P3) PPM, one line of text per pixel. It is much larger than [`ImageFormat::Ppm`]
+    /// and is only there for the tools that expect it.
+    PpmAscii,
+    /// PNG, lossless and compressed, which every viewer and browser opens.
+    Png,
+    /// Portable Float Map: the linear radiance as 32-bit floats, unclamped.
+    Pfm,
+    /// OpenEXR: the linear radiance as 32-bit floats, unclamped, with the coverage
+    /// of the pixels as alpha if `alpha` is set.
+    Exr { compression: ExrCompression, alpha: bool },
+}
+
+impl ImageFormat {
+    /// Picks the format from the extension of `path`, ignoring case.
+    ///
+    /// `.ppm` gives [`ImageFormat::Ppm`]: the ASCII variant has no extension of its own
+    /// and must be asked for explicitly. `.exr` gives a ZIP compressed image without alpha.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Option<ImageFormat> {
+        let extension = path.as_ref().extension()?.to_str()?;
+        match extension.to_ascii_lowercase().as_str() {
+            "ppm" => Some(ImageFormat::Ppm),
+            "png" => Some(ImageFormat::Png),
+            "pfm" => Some(ImageFormat::Pfm),
+            "exr" => Some(ImageFormat::Exr {
+                compression: ExrCompression::default(),
+                alpha: false,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// An in-memory image produced by a [`crate::Renderer`].
+///
+/// Pixels hold linear radiance, already averaged over the samples of the pixel,
+/// and an alpha: the fraction of the samples that hit an object rather than the
+/// background. They are stored row by row, starting from the top-left corner of the image.
+/// [`Aov`]s rendered along with the image are kept with it.
+///
+/// # Example
+///
+/// ```
+/// use raytracer::{Color, FrameBuffer, ImageFormat};
+///
+/// let mut fb = FrameBuffer::new(2, 1);
+/// fb.set(1, 0, Color::new(1.0, 0.0, 0.0));
+/// assert_eq!(fb.get(1, 0).x(), 1.0);
+///
+/// let mut png = Vec::new();
+/// fb.write(&mut png, ImageFormat::Png).unwrap();
+/// assert!(png.starts_with(b"\x89PNG"));
+/// ```
+#[derive(Clone)]
+pub struct FrameBuffer {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+    alpha: Vec<f64>,
+    /// The components of every pixel, one after the other, for each AOV.
+    aovs: Vec<(Aov, Vec<f64>)>,
+}
+
+impl FrameBuffer {
+    /// Creates a black, opaque image of the given size.
+    pub fn new(width: usize, height: usize) -> FrameBuffer {
+        FrameBuffer {
+            width,
+            height,
+            pixels: vec![Color::default(); width * height],
+            alpha: vec![1.0; width * height],
+            aovs: Vec::new(),
+        }
+    }
+
+    /// Builds an image from its pixels, given row by row from the top.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `pixels` does not hold exactly `width * height` colors.
+    pub fn from_pixels(width: usize, height: usize, pixels: Vec<Color>) -> FrameBuffer {
+        assert_eq!(pixels.len(), width * height, "pixel count does not match the size");
+        FrameBuffer {
+            width,
+            height,
+            pixels,
+            alpha: vec![1.0; width * height],
+            aovs: Vec::new(),
+        }
+    }
+
+    /// Replaces the alpha of every pixel, given row by row from the top.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `alpha` does not hold exactly `width * height` values.
+    pub fn with_alpha(mut self, alpha: Vec<f64>) -> FrameBuffer {
+        assert_eq!(alpha.len(), self.width * self.height, "alpha count does not match the size");
+        self.alpha = alpha;
+        self
+    }
+
+    /// Adds `aov` to the image, or replaces it: `values` holds the components of every
+    /// pixel one after the other, row by row from the top.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `values` does not hold exactly `width * height` times the components of `aov`.
+    pub fn with_aov(mut self, aov: Aov, values: Vec<f64>) -> FrameBuffer {
+        assert_eq!(
+            values.len(),
+            self.width * self.height * aov.components(),
+            "AOV size does not match the size"
+        );
+        self.aovs.retain(|(other, _)| *other != aov);
+        self.aovs.push((aov, values));
+        self
+    }
+
+    /// Removes `aov` from the image, if it has it.
+    pub fn without_aov(mut self, aov: Aov) -> FrameBuffer {
+        self.aovs.retain(|(other, _)| *other != aov);
+        self
+    }
+
+    /// Returns a copy of the image with less noise, for previews rendered with few samples.
+    ///
+    /// The filter is guided by the [`Aov::Albedo`] and [`Aov::Normal`] AOVs of the image:
+    /// it blurs the lighting without the textures, and not across edges where the normals
+    /// change. Without them, edges are only found from the colors and the textures are
+    /// blurred too. The alpha and the AOVs are kept as they are.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use raytracer::{Aov, Color, DenoiseSettings, FrameBuffer};
+    ///
+    /// // A gray wall with a speck of noise
+    /// let mut fb = FrameBuffer::from_pixels(5, 5, vec![Color::new(0.5, 0.5, 0.5); 25])
+    ///     .with_aov(Aov::Normal, [0.0, 0.0, 1.0].repeat(25));
+    /// fb.set(2, 2, Color::new(2.0, 2.0, 2.0));
+    ///
+    /// let denoised = fb.denoise(&DenoiseSettings::new());
+    /// assert!(denoised.get(2, 2).x() < 1.0);
+    ///
+    /// // Only 3 passes fit in 5 pixels, the others change nothing
+    /// let many = fb.denoise(&DenoiseSettings::new().iterations(u32::MAX));
+    /// let three = fb.denoise(&DenoiseSettings::new().iterations(3));
+    /// assert_eq!(many.get(2, 2).x(), three.get(2, 2).x());
+    /// ```
+    pub fn denoise(&self, settings: &DenoiseSettings) -> FrameBuffer {
+        let pixels = denoise::denoise(
+            self.width,
+            self.height,
+            &self.pixels,
+            self.aov(Aov::Albedo),
+            self.aov(Aov::Normal),
+            settings,
+        );
+        FrameBuffer {
+            width: self.width,
+            height: self.height,
+            pixels,
+            alpha: self.alpha.clone(),
+            aovs: self.aovs.clone(),
+        }
+    }
+
+    /// Width of the image in pixels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// Height of the image in pixels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// Returns the color of the pixel at column `x` and row `y` (0 is the top row).
+    pub fn get(&self, x: usize, y: usize) -> Color {
+        self.pixels[y * self.width + x]
+    }
+
+    /// Sets the color of the pixel at column `x` and row `y` (0 is the top row).
+    pub fn set(&mut self, x: usize, y: usize, color: Color) {
+        self.pixels[y * self.width + x] = color;
+    }
+
+    /// All the pixels, row by row from the top.
+    pub fn pixels(&self) -> &[Color] {
+        &self.pixels
+    }
+
+    /// Returns the alpha of the pixel at column `x` and row `y` (0 is the top row).
+    pub fn alpha(&self, x: usize, y: usize) -> f64 {
+        self.alpha[y * self.width + x]
+    }
+
+    /// The AOVs of the image, in the order they were added.
+    pub fn aovs(&self) -> impl Iterator<Item = Aov> + '_ {
+        self.aovs.iter().map(|(aov, _)| *aov)
+    }
+
+    /// The components of `aov` for every pixel, one after the other, row by row from the
+    /// top. `None` if the image does not have it.
+    ///
+    /// ```
+    /// use raytracer::{Aov, FrameBuffer};
+    ///
+    /// let fb = FrameBuffer::new(2, 1).with_aov(Aov::Uv, vec![0.25, 0.5, 0.75, 0.5]);
+    /// assert_eq!(&fb.aov(Aov::Uv).unwrap()[2..], [0.75, 0.5]);
+    /// assert!(fb.aov(Aov::Depth).is_none());
+    /// ```
+    pub fn aov(&self, aov: Aov) -> Option<&[f64]> {
+        self.aovs
+            .iter()
+            .find(|(other, _)| *other == aov)
+            .map(|(_, values)| values.as_slice())
+    }
+
+    /// Writes the image in `format`: clamped and sRGB encoded in 8-bit formats, as is
+    /// in float formats.
+    pub fn write(&self, out: &mut impl Write, format: ImageFormat) -> io::Result<()> {
+        self.write_with(out, format, &DisplayTransform::default())
+    }
+
+    /// Writes the image in `format`, turning it into display colors with `display` in
+    /// 8-bit formats. Float formats keep the linear radiance and ignore `display`.
+    pub fn write_with(
+        &self,
+        out: &mut impl Write,
+        format: ImageFormat,
+        display: &DisplayTransform,
+    ) -> io::Result<()> {
+        match format {
+            ImageFormat::Ppm => self.encode_ppm_binary(out, display),
+            ImageFormat::PpmAscii => self.encode_ppm(out, display),
+            ImageFormat::Png => self.encode_png(out, display),
+            ImageFormat::Pfm => self.write_pfm(out),
+            ImageFormat::Exr { compression, alpha } => self.write_exr(out, compression, alpha),
+        }
+    }
+
+    /// Writes the image as an ASCII (P3) PPM, clamped and sRGB encoded.
+    pub fn write_ppm(&self, out: &mut impl Write) -> io::Result<()> {
+        self.encode_ppm(out, &DisplayTransform::default())
+    }
+
+    /// Writes the image as a binary (P6) PPM, clamped and sRGB encoded.
+    pub fn write_ppm_binary(&self, out: &mut impl Write) -> io::Result<()> {
+        self.encode_ppm_binary(out, &DisplayTransform::default())
+    }
+
+    /// Writes the image as an 8-bit RGB PNG, clamped and sRGB encoded.
+    pub fn write_png(&self, out: &mut impl Write) -> io::Result<()> {
+        self.encode_png(out, &DisplayTransform::default())
+    }
+
+    fn encode_ppm(&self, out: &mut impl Write, display: &DisplayTransform) -> io::Result<()> {
+        writeln!(out, "P3\n{} {}\n255", self.width, self.height)?;
+        for &pixel in &self.pixels {
+            let [r, g, b] = display.to_rgb8(pixel);
+            writeln!(out, "{} {} {}", r, g, b)?;
+        }
+        Ok(())
+    }
+
+    fn encode_ppm_binary(&self, out: &mut impl Write, display: &DisplayTransform) -> io::Result<()> {
+        writeln!(out, "P6\n{} {}\n255", self.width, self.height)?;
+        out.write_all(&self.to_rgb8(display))
+    }
+
+    fn encode_png(&self, out: &mut impl Write, display: &DisplayTransform) -> io::Result<()> {
+        let mut encoder = png::Encoder::new(out, self.width as u32, self.height as u32);
+        encoder.set_color(png::ColorType::Rgb);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&self.to_rgb8(display))?;
+        writer.finish()?;
+        Ok(())
+    }
+
+    /// Writes the linear radiance as a color Portable Float Map, little-endian.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if the image is empty, which the
+    /// format cannot describe.
+    pub fn write_pfm(&self, out: &mut impl Write) -> io::Result<()> {
+        self.check_not_empty()?;
+        // A negative scale marks little-endian floats
+        writeln!(out, "PF\n{} {}\n-1.0", self.width, self.height)?;
+        let mut data = Vec::with_capacity(self.pixels.len() * 12);
+        // Rows go from the bottom of the image to its top
+        for row in self.pixels.chunks(self.width.max(1)).rev() {
+            for pixel in row {
+                for component in [pixel.x(), pixel.y(), pixel.z()] {
+                    data.extend_from_slice(&(component as f32).to_le_bytes());
+                }
+            }
+        }
+        out.write_all(&data)
+    }
+
+    /// Writes the linear radiance as an OpenEXR image of `R`, `G` and `B` float channels,
+    /// and an `A` channel holding the alpha if `alpha` is set.
+    ///
+    /// Every AOV of the image is written as a layer of the same image, with channels
+    /// such as `normal.X`.
+    ///
+    /// The colors include what the background adds to partly covered pixels: render
+    /// with a black background to get colors premultiplied by the alpha, as
+    /// compositing expects.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if the image is empty, which the
+    /// format cannot describe.
+    ///
+    /// ```
+    /// use std::io::ErrorKind;
+    /// use raytracer::{ExrCompression, FrameBuffer};
+    ///
+    /// let empty = FrameBuffer::new(0, 4);
+    /// let err = empty.write_exr(&mut Vec::new(), ExrCompression::Zip, false).unwrap_err();
+    /// assert_eq!(err.kind(), ErrorKind::InvalidInput);
+    /// ```
+    pub fn write_exr(
+        &self,
+        out: &mut impl Write,
+        compression: ExrCompression,
+        alpha: bool,
+    ) -> io::Result<()> {
+        self.check_not_empty()?;
+        let channel = |name: &str, component: fn(&Color) -> f64| {
+            Channel::new(name, self.pixels.iter().map(|p| component(p) as f32).collect())
+        };
+        let mut channels = vec![channel("R", Color::x), channel("G", Color::y), channel("B", Color::z)];
+        if alpha {
+            channels.push(Channel::new("A", self.alpha.iter().map(|&a| a as f32).collect()));
+        }
+        for (aov, values) in &self.aovs {
+            for (i, name) in aov.channels().iter().enumerate() {
+                let samples = values.iter().skip(i).step_by(aov.components()).map(|&v| v as f32).collect();
+                channels.push(Channel::new(format!("{}.{}", aov.name(), name), samples));
+            }
+        }
+        exr::write_exr(out, self.width, self.height, channels, compression)
+    }
+
+    /// Writes `aov` alone, as floats, in `format`: PFM or OpenEXR.
+    ///
+    /// Its components become the channels of a color image, in order, or of a grayscale one
+    /// if it has a single component. The `uv` of a PFM image has a blue channel of 0.
+    ///
+    /// Fails with [`io::ErrorKind::InvalidInput`] if the image does not have `aov`, if
+    /// `format` is an 8-bit format, which could not hold it, or if the image is empty.
+    pub fn write_aov(&self, out: &mut impl Write, aov: Aov, format: ImageFormat) -> io::Result<()> {
+        self.check_not_empty()?;
+        let values = self.aov(aov).ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidInput, format!("the image has no {} AOV", aov.name()))
+        })?;
+        let components = aov.components();
+        match format {
+            ImageFormat::Pfm => {
+                // Grayscale maps are "Pf", color ones "PF"
+                let channels = if components == 1 { 1 } else { 3 };
+                let kind = if channels == 1 { "Pf" } else { "PF" };
+                writeln!(out, "{}\n{} {}\n-1.0", kind, self.width, self.height)?;
+                let mut data = Vec::with_capacity(values.len() / components * channels * 4);
+                for row in values.chunks((self.width * components).max(1)).rev() {
+                    for pixel in row.chunks(components) {
+                        for c in 0..channels {
+                            let value = pixel.get(c).copied().unwrap_or_default();
+                            data.extend_from_slice(&(value as f32).to_le_bytes());
+                        }
+                    }
+                }
+                out.write_all(&data)
+            }
+            ImageFormat::Exr { compression, .. } => {
+                let names: &[&str] = if components == 1 { &["Y"] } else { &["R", "G", "B"] };
+                let channels = names
+                    .iter()
+                    .take(components)
+                    .enumerate()
+                    .map(|(i, name)| {
+                        let samples = values.iter().skip(i).step_by(components).map(|&v| v as f32).collect();
+                        Channel::new(*name, samples)
+                    })
+                    .collect();
+                exr::write_exr(out, self.width, self.height, channels, compression)
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "AOVs can only be written as PFM or OpenEXR images",
+            )),
+        }
+    }
+
+    /// Float formats have no way to describe an image without pixels.
+    fn check_not_empty(&self) -> io::Result<()> {
+        if self.width == 0 || self.height == 0 {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("cannot write an empty image of {}x{} pixels", self.width, self.height),
+            ));
+        }
+        Ok(())
+    }
+
+    /// The 8-bit components of every pixel, row by row from the top.
+    fn to_rgb8(&self, display: &DisplayTransform) -> Vec<u8> {
+        self.pixels
+            .iter()
+            .flat_map(|&pixel| display.to_rgb8(pixel))
+            .collect()
+    }
+}
@@ -1,13 +1,16 @@
 use std::sync::Arc;
 
+use crate::aabb::Aabb;
 use crate::material::Material;
 use crate::ray::Ray;
+use crate::vec2::UV;
 use crate::vec3::{self, Point3, Vec3};
 
 /// Represents a hit record for a ray-object intersection.
 /// 
 /// This struct stores details about where a ray intersects an object, including the intersection point,
-/// surface normal, material, distance along the ray, and whether the intersection is on the front face.
+/// surface normal, material, distance along the ray, whether the intersection is on the front face
+/// and where on the surface it is, for textures.
 /// 
 /// Imagine shining a laser pointer at an object. This structure keeps track of where the
 /// laser hits and how it bounces.
@@ -17,6 +20,7 @@ pub struct HitRecord {
     pub mat: Arc<dyn Material>, // Material of the hit object
     pub t: f64, // Distance from ray origin to intersection
     pub front_face: bool, // Whether the hit is on the front face of the object
+    pub uv: UV, // Texture coordinates of the intersection point on the surface
 }
 
 impl HitRecord {
@@ -49,4 +53,45 @@ pub trait Hittable: Send + Sync {
     /// - `t_max`: The maximum distance allowed for a valid hit.
     /// - Returns `Some(HitRecord)` if there is a hit, otherwise `None`.
     fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord>;
-}
\ No newline at end of file
+
+    /// Returns a box containing the whole object.
+    ///
+    /// Used by acceleration structures such as `BvhNode` to skip objects a ray cannot hit.
+    fn bounding_box(&self) -> Aabb;
+
+    /// Returns the density, per unit of solid angle, with which [`Hittable::random`]
+    /// picks `direction` from `origin`.
+    ///
+    /// Objects that can be sampled as lights implement this along with `random`;
+    /// the others are never picked, their density is 0.
+    fn pdf_value(&self, _origin: Point3, _direction: Vec3) -> f64 {
+        0.0
+    }
+
+    /// Returns a random direction from `origin` towards a point of the object,
+    /// not necessarily of unit length.
+    ///
+    /// Used to send shadow rays towards lights, see [`Hittable::pdf_value`].
+    fn random(&self, _origin: Point3) -> Vec3 {
+        Vec3::new(1.0, 0.0, 0.0)
+    }
+}
+/// A shared object can be hit like the object itself, so the same geometry can be
+/// referenced from several places, for example by [`Instance`](crate::Instance)s.
+impl<T: Hittable + ?Sized> Hittable for Arc<T> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        (**self).hit(ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        (**self).bounding_box()
+    }
+
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        (**self).pdf_value(origin, direction)
+    }
+
+    fn random(&self, origin: Point3) -> Vec3 {
+        (**self).random(origin)
+    }
+}
@@ -1,5 +1,8 @@
+use crate::aabb::Aabb;
+use crate::common;
 use crate::hittable::{HitRecord, Hittable};
 use crate::ray::Ray;
+use crate::vec3::{Point3, Vec3};
  
 /// A list of objects that implement the `Hittable` trait.
 /// This struct is useful for managing multiple hittable objects in a ray tracing scene.
@@ -7,20 +10,19 @@ use crate::ray::Ray;
 /// # Example
 ///
 /// ```
-/// use crate::hittable::HittableList;
-/// use crate::sphere::Sphere;
-/// use crate::vec3::Point3;
+/// use raytracer::{Color, HittableList, Lambertian, Point3, Sphere};
 /// use std::sync::Arc;
-/// use crate::material::Lambertian;
 ///
 /// let mut world = HittableList::new();
-/// let material = Arc::new(Lambertian::new(crate::vec3::Color::new(0.8, 0.3, 0.3)));
+/// let material = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3)));
 /// world.add(Box::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, material)));
 /// ```
 #[derive(Default)]
 pub struct HittableList {
     /// A vector storing objects that implement the `Hittable` trait.
     objects: Vec<Box<dyn Hittable>>,
+    /// A box containing every object of the list.
+    bbox: Aabb,
 }
 
 impl HittableList {
@@ -33,6 +35,7 @@ impl HittableList {
     /// # Example
     ///
     /// ```
+    /// # use raytracer::HittableList;
     /// let list = HittableList::new();
     /// ```
     pub fn new() -> HittableList {
@@ -48,12 +51,31 @@ impl HittableList {
     /// # Example
     ///
     /// ```
+    /// # use raytracer::{Color, HittableList, Lambertian, Point3, Sphere};
+    /// # use std::sync::Arc;
+    /// # let material = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3)));
     /// let mut list = HittableList::new();
-    /// list.add(Box::new(SomeHittableObject));
+    /// list.add(Box::new(Sphere::new(Point3::new(0.0, 0.0, -1.0), 0.5, material)));
     /// ```
     pub fn add(&mut self, object: Box<dyn Hittable>) {
+        self.bbox = Aabb::surrounding(&self.bbox, &object.bounding_box());
         self.objects.push(object);
     }
+
+    /// Returns the number of objects in the list.
+    pub fn len(&self) -> usize {
+        self.objects.len()
+    }
+
+    /// Returns true if the list holds no object.
+    pub fn is_empty(&self) -> bool {
+        self.objects.is_empty()
+    }
+
+    /// Consumes the list and returns its objects, e.g. to build a `BvhNode` from them.
+    pub fn into_objects(self) -> Vec<Box<dyn Hittable>> {
+        self.objects
+    }
 }
 
 impl Hittable for HittableList {
@@ -81,4 +103,26 @@ impl Hittable for HittableList {
 
         temp_rec
     }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+
+    /// Averages the densities of the objects, as [`Hittable::random`] picks one of them at random.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        if self.objects.is_empty() {
+            return 0.0;
+        }
+        let sum: f64 = self.objects.iter().map(|object| object.pdf_value(origin, direction)).sum();
+        sum / self.objects.len() as f64
+    }
+
+    /// Returns a random direction towards one of the objects, all being equally likely.
+    fn random(&self, origin: Point3) -> Vec3 {
+        if self.objects.is_empty() {
+            return Vec3::new(1.0, 0.0, 0.0);
+        }
+        let index = (common::random_double() * self.objects.len() as f64) as usize;
+        self.objects[index.min(self.objects.len() - 1)].random(origin)
+    }
 }
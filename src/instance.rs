@@ -0,0 +1,86 @@
+use std::sync::Arc;
+
+use crate::aabb::Aabb;
+use crate::hittable::{HitRecord, Hittable};
+use crate::material::Material;
+use crate::ray::Ray;
+use crate::transform::Transform;
+
+/// A placement of a shared object, the prototype, with its own transform and
+/// optionally its own material.
+///
+/// Instances only hold a reference to their prototype, so a complex object can be
+/// placed many times for the memory of one. Putting the instances in a
+/// [`Bvh`](crate::Bvh) gives a two-level hierarchy: the top level finds the
+/// instances a ray may hit, and the prototype, itself a `Bvh` or a
+/// [`TriangleMesh`](crate::TriangleMesh), finds the closest hit in its own space.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use raytracer::*;
+///
+/// let wood = Arc::new(Lambertian::new(Color::new(0.4, 0.3, 0.2)));
+/// let mut post = HittableList::new();
+/// post.add(Box::new(Cylinder::new(Point3::new(0.0, 0.5, 0.0), 0.1, 1.0, wood)));
+/// let post: Arc<dyn Hittable> = Arc::new(Bvh::new(post));
+///
+/// // A fence of a thousand posts sharing the same geometry
+/// let mut fence = HittableList::new();
+/// for i in 0..1000 {
+///     let transform = Transform::translate(Vec3::new(i as f64 * 0.5, 0.0, 0.0));
+///     fence.add(Box::new(Instance::new(post.clone(), transform)));
+/// }
+/// let fence = Bvh::new(fence);
+/// ```
+pub struct Instance {
+    prototype: Arc<dyn Hittable>,
+    transform: Transform,
+    /// Replaces the materials of the prototype when set.
+    material: Option<Arc<dyn Material>>,
+    bbox: Aabb,
+}
+
+impl Instance {
+    /// Places `prototype` in the world with `transform`.
+    pub fn new(prototype: Arc<dyn Hittable>, transform: Transform) -> Instance {
+        let bbox = transform.bounding_box(&prototype.bounding_box());
+        Instance {
+            prototype,
+            transform,
+            material: None,
+            bbox,
+        }
+    }
+
+    /// Renders this instance with `mat`, whatever the materials of the prototype.
+    pub fn with_material(mut self, mat: Arc<dyn Material>) -> Instance {
+        self.material = Some(mat);
+        self
+    }
+
+    /// The shared object this instance places.
+    pub fn prototype(&self) -> &Arc<dyn Hittable> {
+        &self.prototype
+    }
+
+    /// The transform placing the prototype in the world.
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+}
+
+impl Hittable for Instance {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let mut rec = self.transform.hit_object(self.prototype.as_ref(), ray, t_min, t_max)?;
+        if let Some(mat) = &self.material {
+            rec.mat = mat.clone();
+        }
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
@@ -1,31 +1,68 @@
 #![allow(dead_code)]
 
+mod aabb;
+mod aov;
+mod bvh;
 mod camera;
 pub mod color;
 pub mod common;
+mod framebuffer;
 mod hittable;
 mod hittable_list;
 mod material;
+pub mod parsing;
 mod ray;
+mod renderer;
+mod scene;
+mod tonemap;
 mod sphere;
 pub mod vec3;
 mod quad;
 mod cube;
 mod cylinder;
+mod denoise;
+mod faceted_cylinder;
 mod disk;
+mod exr;
 mod texture;
+mod triangle;
+mod mesh;
+mod mat4;
+mod transform;
+mod instance;
 mod vec2;
 
 pub use quad::Quad;
 // pub use rayon::prelude::*;
 
+pub use aabb::Aabb;
+pub use aov::Aov;
+pub use bvh::{Bvh, BvhNode};
 pub use camera::Camera;
 pub use color::Color;
-pub use hittable::Hittable;
+pub use hittable::{HitRecord, Hittable};
 pub use hittable_list::HittableList;
-pub use material::{Dielectric, Lambertian, Metal};
+pub use material::{BsdfSample, Dielectric, DiffuseLight, Lambertian, Material, Metal, ScatterRecord};
 pub use ray::Ray;
 pub use sphere::Sphere;
 pub use vec3::{Point3,Vec3};
 pub use cube::Cube;
-pub use cylinder::Cylinder;
\ No newline at end of file
+pub use cylinder::Cylinder;
+pub use faceted_cylinder::FacetedCylinder;
+pub use disk::Disk;
+pub use triangle::Triangle;
+pub use mesh::{FaceGroup, TriangleMesh};
+pub use mat4::Mat4;
+pub use transform::{Transform, Transformed};
+pub use instance::Instance;
+pub use texture::{
+    CheckerTexture, ColorRamp, Filter, ImageError, ImageTexture, MarbleTexture, NoiseTexture, Perlin,
+    SolidColor, Texture, WoodTexture, WrapMode,
+};
+pub use vec2::{Vec2, UV};
+pub use scene::Scene;
+pub use denoise::DenoiseSettings;
+pub use exr::ExrCompression;
+pub use framebuffer::{FrameBuffer, ImageFormat};
+pub use renderer::{Background, RenderSettings, Renderer};
+pub use tonemap::{DisplayTransform, ToneMap};
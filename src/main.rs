@@ -1,63 +1,123 @@
+mod cli;
+
 use raytracer::*;
 use std::fs::File;
 use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process;
 use std::sync::Arc;
-use rayon::prelude::*;
+
+use cli::{Command, RenderArgs};
 
 const ASPECT_RATIO: f64 = 3.0 / 2.0;
-const IMAGE_WIDTH: i32 = 600;
-const IMAGE_HEIGHT: i32 = (IMAGE_WIDTH as f64 / ASPECT_RATIO) as i32;
-const SAMPLES_PER_PIXEL: i32 = 100;
-const MAX_DEPTH: i32 = 25;
-
-fn ray_color(r: &Ray, world: &dyn Hittable, depth: i32) -> Color {
-    if depth <= 0 {
-        return Color::new(0.0, 0.0, 0.0);
+const IMAGE_WIDTH: usize = 600;
+const SAMPLES_PER_PIXEL: u32 = 100;
+const MAX_DEPTH: u32 = 25;
+
+/// A scene compiled into the binary.
+struct BuiltinScene {
+    name: &'static str,
+    description: &'static str,
+    build: fn() -> Scene,
+}
+
+const BUILTIN_SCENES: [BuiltinScene; 6] = [
+    BuiltinScene { name: "scene1", description: "a single red sphere", build: scene1_sphere },
+    BuiltinScene { name: "scene2", description: "a metal cube on a plane", build: scene2_plane_cube },
+    BuiltinScene {
+        name: "scene3",
+        description: "a sphere, a cube and a cylinder on a plane",
+        build: scene3_all_objects,
+    },
+    BuiltinScene {
+        name: "scene4",
+        description: "scene3 seen from another angle",
+        build: scene4_all_objects_alt_cam,
+    },
+    BuiltinScene {
+        name: "forest",
+        description: "100,000 instances of a single tree",
+        build: scene5_forest,
+    },
+    BuiltinScene {
+        name: "cornell",
+        description: "the Cornell box, lit only by its ceiling light",
+        build: scene6_cornell_box,
+    },
+];
+
+/// Renders `scene` and writes the image to `filename` in `format`, shown with
+/// `display` if it is an 8-bit format.
+///
+/// The AOVs of `settings` are layers of EXR images. Other images get a float file
+/// per AOV next to them, PFM next to PFM images and EXR otherwise.
+///
+/// With `denoise`, the image is filtered before being written, guided by its albedo
+/// and normals, which are rendered for it but only written if `settings` asked for them.
+fn render_scene(
+    filename: &Path,
+    format: ImageFormat,
+    display: &DisplayTransform,
+    scene: Scene,
+    mut settings: RenderSettings,
+    denoise: bool,
+) -> std::io::Result<()> {
+    let requested = settings.aovs.clone();
+    if denoise {
+        for aov in [Aov::Albedo, Aov::Normal] {
+            if !settings.aovs.contains(&aov) {
+                settings.aovs.push(aov);
+            }
+        }
     }
 
-    if let Some(hit_rec) = world.hit(r, 0.001, common::INFINITY) {
-        if let Some(scatter_rec) = hit_rec.mat.scatter(r, &hit_rec) {
-            return scatter_rec.attenuation * ray_color(&scatter_rec.scattered, world, depth - 1);
+    let world = Bvh::new(scene.world);
+    let renderer = Renderer::new(settings).with_lights(scene.lights);
+    let mut image = renderer.render_with_progress(&world, &scene.camera, |done, total| {
+        eprint!("\rRendering {} | Scanlines remaining: {} ", filename.display(), total - done);
+    });
+    if denoise {
+        image = image.denoise(&DenoiseSettings::new());
+        for aov in [Aov::Albedo, Aov::Normal] {
+            if !requested.contains(&aov) {
+                image = image.without_aov(aov);
+            }
         }
-        return Color::new(0.0, 0.0, 0.0);
     }
 
-    let unit_direction = vec3::unit_vector(r.direction());
-    let t = 0.5 * (unit_direction.y() + 1.0);
-    (1.0 - t) * Color::new(1.0, 1.0, 1.0) + t * Color::new(0.5, 0.7, 1.0)
-}
+    let mut writer = BufWriter::new(File::create(filename)?);
+    image.write_with(&mut writer, format, display)?;
+    writer.flush()?;
+    eprintln!("\n{} Done.", filename.display());
 
-fn render_scene(filename: &str, scene: HittableList, cam: Camera) {
-    let file = File::create(filename).unwrap();
-    let mut writer = BufWriter::new(file);
-
-    writeln!(writer, "P3\n{} {}\n255", IMAGE_WIDTH, IMAGE_HEIGHT).unwrap();
-
-    for j in (0..IMAGE_HEIGHT).rev() {
-        eprint!("\rRendering {} | Scanlines remaining: {} ", filename, j);
-        let pixel_colors: Vec<_> = (0..IMAGE_WIDTH)
-            .into_par_iter()
-            .map(|i| {
-                let mut pixel_color = Color::new(0.0, 0.0, 0.0);
-                for _ in 0..SAMPLES_PER_PIXEL {
-                    let u = ((i as f64) + common::random_double()) / (IMAGE_WIDTH - 1) as f64;
-                    let v = ((j as f64) + common::random_double()) / (IMAGE_HEIGHT - 1) as f64;
-                    let r = cam.get_ray(u, v);
-                    pixel_color += ray_color(&r, &scene, MAX_DEPTH);
-                }
-                pixel_color
-            })
-            .collect();
-
-        for color in pixel_colors {
-            color::write_color(&mut writer, color, SAMPLES_PER_PIXEL);
+    if !matches!(format, ImageFormat::Exr { .. }) {
+        let aov_format = match format {
+            ImageFormat::Pfm => ImageFormat::Pfm,
+            _ => ImageFormat::Exr {
+                compression: ExrCompression::default(),
+                alpha: false,
+            },
+        };
+        for aov in image.aovs() {
+            let path = aov_path(filename, aov, aov_format);
+            let mut writer = BufWriter::new(File::create(&path)?);
+            image.write_aov(&mut writer, aov, aov_format)?;
+            writer.flush()?;
+            eprintln!("{} Done.", path.display());
         }
     }
+    Ok(())
+}
 
-    eprintln!("\n{} Done.", filename);
+/// The file of `aov` next to the image `filename`: `cornell.png` gets `cornell.normal.exr`.
+fn aov_path(filename: &Path, aov: Aov, format: ImageFormat) -> PathBuf {
+    let extension = if format == ImageFormat::Pfm { "pfm" } else { "exr" };
+    let mut name = filename.file_stem().unwrap_or_default().to_os_string();
+    name.push(format!(".{}.{}", aov.name(), extension));
+    filename.with_file_name(name)
 }
 
-fn scene1_sphere() -> (HittableList, Camera) {
+fn scene1_sphere() -> Scene {
     let mut world = HittableList::new();
     let sphere = Arc::new(Lambertian::new(Color::new(0.9, 0.2, 0.3)));
     world.add(Box::new(Sphere::new(Point3::new(0.0, 0.5, 0.0), 0.5, sphere)));
@@ -72,10 +132,10 @@ fn scene1_sphere() -> (HittableList, Camera) {
         1.0,
     );
 
-    (world, cam)
+    Scene::new(world, cam)
 }
 
-fn scene2_plane_cube() -> (HittableList, Camera) {
+fn scene2_plane_cube() -> Scene {
     let mut world = HittableList::new();
 
     let ground = Arc::new(Lambertian::new(Color::new(0.4, 0.4, 0.4)));
@@ -94,10 +154,10 @@ fn scene2_plane_cube() -> (HittableList, Camera) {
         1.0,
     );
 
-    (world, cam)
+    Scene::new(world, cam)
 }
 
-fn scene3_all_objects() -> (HittableList, Camera) {
+fn scene3_all_objects() -> Scene {
     let mut world = HittableList::new();
 
     let ground = Arc::new(Lambertian::new(Color::new(0.5, 0.5, 0.5)));
@@ -107,7 +167,7 @@ fn scene3_all_objects() -> (HittableList, Camera) {
 
     world.add(Box::new(Sphere::new(Point3::new(-1.0, 0.5, 0.0), 0.5, glass)));
     world.add(Box::new(Cube::from_center(Point3::new(1.0, 0.5, 0.0), 1.0, metal.clone())));
-    world.add(Box::new(Cylinder::new(Point3::new(0.0, 0.5, 1.0), 0.3, 1.0, matte, 32)));
+    world.add(Box::new(Cylinder::new(Point3::new(0.0, 0.5, 1.0), 0.3, 1.0, matte)));
     world.add(Box::new(Sphere::new(Point3::new(0.0, -1000.0, 0.0), 1000.0, ground)));
 
     let cam = Camera::new(
@@ -120,11 +180,11 @@ fn scene3_all_objects() -> (HittableList, Camera) {
         10.0,
     );
 
-    (world, cam)
+    Scene::new(world, cam)
 }
 
-fn scene4_all_objects_alt_cam() -> (HittableList, Camera) {
-    let (world, _) = scene3_all_objects();
+fn scene4_all_objects_alt_cam() -> Scene {
+    let world = scene3_all_objects().world;
 
     let cam = Camera::new(
         Point3::new(-3.0, 4.0, 3.0),
@@ -136,18 +196,201 @@ fn scene4_all_objects_alt_cam() -> (HittableList, Camera) {
         10.0,
     );
 
-    (world, cam)
+    Scene::new(world, cam)
 }
 
-fn main() {
-    let scenes = [
-        ("scene1.ppm", scene1_sphere()),
-        ("scene2.ppm", scene2_plane_cube()),
-        ("scene3.ppm", scene3_all_objects()),
-        ("scene4.ppm", scene4_all_objects_alt_cam()),
+fn scene5_forest() -> Scene {
+    const ROWS: usize = 316;
+    const SPACING: f64 = 1.2;
+
+    // The same forest on every run
+    common::seed_random(5);
+
+    let bark = Arc::new(Lambertian::new(Color::new(0.35, 0.25, 0.15)));
+    let leaves = Arc::new(Lambertian::new(Color::new(0.15, 0.45, 0.15)));
+    let autumn = [
+        Arc::new(Lambertian::new(Color::new(0.8, 0.4, 0.1))),
+        Arc::new(Lambertian::new(Color::new(0.7, 0.15, 0.1))),
+        Arc::new(Lambertian::new(Color::new(0.8, 0.7, 0.2))),
     ];
 
-    for (filename, (scene, cam)) in scenes {
-        render_scene(filename, scene, cam);
+    // Trunks and crowns are separate prototypes, so crowns alone can change color
+    let mut trunk = HittableList::new();
+    trunk.add(Box::new(Cylinder::between(Point3::new(0.0, 0.0, 0.0), Point3::new(0.0, 1.0, 0.0), 0.08, bark)));
+    let trunk: Arc<dyn Hittable> = Arc::new(Bvh::new(trunk));
+
+    let mut crown = HittableList::new();
+    crown.add(Box::new(Sphere::new(Point3::new(0.0, 1.1, 0.0), 0.45, leaves.clone())));
+    crown.add(Box::new(Sphere::new(Point3::new(0.0, 1.5, 0.0), 0.35, leaves.clone())));
+    crown.add(Box::new(Sphere::new(Point3::new(0.0, 1.85, 0.0), 0.22, leaves)));
+    let crown: Arc<dyn Hittable> = Arc::new(Bvh::new(crown));
+
+    let mut forest = HittableList::new();
+    for row in 0..ROWS {
+        for col in 0..ROWS {
+            let x = (col as f64 - ROWS as f64 / 2.0 + common::random_double()) * SPACING;
+            let z = -(row as f64 + common::random_double()) * SPACING;
+            let transform = Transform::rotate_y(360.0 * common::random_double())
+                .then(&Transform::uniform_scale(common::random_double_range(0.7, 1.3)))
+                .then(&Transform::translate(Vec3::new(x, 0.0, z)));
+
+            forest.add(Box::new(Instance::new(trunk.clone(), transform)));
+            let mut tree_crown = Instance::new(crown.clone(), transform);
+            if common::random_double() < 0.25 {
+                let color = (common::random_double() * autumn.len() as f64) as usize;
+                tree_crown = tree_crown.with_material(autumn[color].clone());
+            }
+            forest.add(Box::new(tree_crown));
+        }
+    }
+
+    // The forest is a single object of the world, a hierarchy over the instances
+    let mut world = HittableList::new();
+    let ground = Arc::new(Lambertian::new(Color::new(0.4, 0.35, 0.25)));
+    world.add(Box::new(Bvh::new(forest)));
+    world.add(Box::new(Sphere::new(Point3::new(0.0, -10000.0, 0.0), 10000.0, ground)));
+
+    let cam = Camera::new(
+        Point3::new(0.0, 15.0, 20.0),
+        Point3::new(0.0, 0.0, -60.0),
+        Point3::new(0.0, 1.0, 0.0),
+        40.0,
+        ASPECT_RATIO,
+        0.0,
+        80.0,
+    );
+
+    Scene::new(world, cam)
+}
+
+fn scene6_cornell_box() -> Scene {
+    let mut world = HittableList::new();
+
+    let red = Arc::new(Lambertian::new(Color::new(0.65, 0.05, 0.05)));
+    let white = Arc::new(Lambertian::new(Color::new(0.73, 0.73, 0.73)));
+    let green = Arc::new(Lambertian::new(Color::new(0.12, 0.45, 0.15)));
+    let light = Arc::new(DiffuseLight::new(Color::new(15.0, 15.0, 15.0)));
+
+    world.add(Box::new(Quad::new(Point3::new(555.0, 0.0, 0.0), Vec3::new(0.0, 555.0, 0.0), Vec3::new(0.0, 0.0, 555.0), green)));
+    world.add(Box::new(Quad::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(0.0, 555.0, 0.0), Vec3::new(0.0, 0.0, 555.0), red)));
+    // The ceiling light is also sampled directly
+    let ceiling_light = Arc::new(Quad::new(Point3::new(343.0, 554.0, 332.0), Vec3::new(-130.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -105.0), light));
+    world.add(Box::new(ceiling_light.clone()));
+    let mut lights = HittableList::new();
+    lights.add(Box::new(ceiling_light));
+    world.add(Box::new(Quad::new(Point3::new(0.0, 0.0, 0.0), Vec3::new(555.0, 0.0, 0.0), Vec3::new(0.0, 0.0, 555.0), white.clone())));
+    world.add(Box::new(Quad::new(Point3::new(555.0, 555.0, 555.0), Vec3::new(-555.0, 0.0, 0.0), Vec3::new(0.0, 0.0, -555.0), white.clone())));
+    world.add(Box::new(Quad::new(Point3::new(0.0, 0.0, 555.0), Vec3::new(555.0, 0.0, 0.0), Vec3::new(0.0, 555.0, 0.0), white.clone())));
+
+    let tall_box = Cube::new(Point3::new(0.0, 0.0, 0.0), Point3::new(165.0, 330.0, 165.0), white.clone());
+    world.add(Box::new(Transformed::new(
+        Box::new(tall_box),
+        Transform::rotate_y(15.0).then(&Transform::translate(Vec3::new(265.0, 0.0, 295.0))),
+    )));
+    let short_box = Cube::new(Point3::new(0.0, 0.0, 0.0), Point3::new(165.0, 165.0, 165.0), white);
+    world.add(Box::new(Transformed::new(
+        Box::new(short_box),
+        Transform::rotate_y(-18.0).then(&Transform::translate(Vec3::new(130.0, 0.0, 65.0))),
+    )));
+
+    let cam = Camera::new(
+        Point3::new(278.0, 278.0, -800.0),
+        Point3::new(278.0, 278.0, 0.0),
+        Point3::new(0.0, 1.0, 0.0),
+        40.0,
+        1.0,
+        0.0,
+        10.0,
+    );
+
+    Scene::new(world, cam)
+        .with_background(Background::Solid(Color::new(0.0, 0.0, 0.0)))
+        .with_lights(lights)
+}
+
+/// Loads `name` as a built-in scene, or else as a JSON scene file.
+fn load(name: &str) -> Result<Scene, String> {
+    if let Some(builtin) = BUILTIN_SCENES.iter().find(|builtin| builtin.name == name) {
+        return Ok((builtin.build)());
+    }
+    parsing::load_scene(name).map_err(|err| format!("{}: {}", name, err))
+}
+
+fn render(args: RenderArgs) -> Result<(), String> {
+    let mut scene = load(&args.scene)?;
+
+    if let Some(ratio) = args.aspect_ratio {
+        scene.camera.set_aspect_ratio(ratio);
+    }
+    let width = args.width.unwrap_or(IMAGE_WIDTH);
+    let height = ((width as f64 / scene.camera.aspect_ratio()).round() as usize).max(1);
+
+    let output = args.output.unwrap_or_else(|| {
+        let stem = Path::new(&args.scene).file_stem().unwrap_or_default();
+        PathBuf::from(stem).with_extension("png")
+    });
+    let mut format = args
+        .format
+        .or_else(|| ImageFormat::from_path(&output))
+        .ok_or_else(|| {
+            format!(
+                "{}: unsupported output format, expected a .png, .ppm, .pfm or .exr file",
+                output.display()
+            )
+        })?;
+    match &mut format {
+        ImageFormat::Exr { compression, alpha } => {
+            *compression = args.compression.unwrap_or(*compression);
+            *alpha = args.alpha;
+        }
+        _ if args.compression.is_some() || args.alpha => {
+            return Err(format!("{}: --compression and --alpha need an EXR image", output.display()));
+        }
+        _ => {}
+    }
+
+    if let Some(threads) = args.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()
+            .map_err(|err| err.to_string())?;
+    }
+
+    let mut settings = RenderSettings::new()
+        .resolution(width, height)
+        .samples_per_pixel(args.samples_per_pixel.unwrap_or(SAMPLES_PER_PIXEL))
+        .max_depth(args.max_depth.unwrap_or(MAX_DEPTH))
+        .background(args.background.unwrap_or(scene.background));
+    settings.seed = args.seed;
+    settings.aovs = args.aovs;
+    let display = DisplayTransform::new()
+        .exposure(args.exposure.unwrap_or(0.0))
+        .tone_map(args.tone_map.unwrap_or_default());
+    render_scene(&output, format, &display, scene, settings, args.denoise)
+        .map_err(|err| format!("{}: {}", output.display(), err))
+}
+
+fn main() {
+    let command = match cli::parse(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(err) => {
+            eprintln!("error: {}\n\n{}", err, cli::USAGE);
+            process::exit(2);
+        }
+    };
+
+    match command {
+        Command::Render(args) => {
+            if let Err(err) = render(*args) {
+                eprintln!("error: {}", err);
+                process::exit(1);
+            }
+        }
+        Command::ListScenes => {
+            for builtin in BUILTIN_SCENES {
+                println!("{:<8} {}", builtin.name, builtin.description);
+            }
+        }
+        Command::Help => println!("{}", cli::USAGE),
     }
 }
@@ -0,0 +1,142 @@
+use std::ops::Mul;
+
+use crate::vec3::{Point3, Vec3};
+
+/// A 4x4 matrix acting on points and vectors in homogeneous coordinates.
+///
+/// Points have an implicit fourth coordinate of 1, so they are moved by the
+/// translation part of the matrix; vectors have a fourth coordinate of 0 and are not.
+///
+/// # Example
+///
+/// ```
+/// use raytracer::{Mat4, Point3, Vec3};
+///
+/// let m = Mat4::new([
+///     [1.0, 0.0, 0.0, 2.0],
+///     [0.0, 1.0, 0.0, 0.0],
+///     [0.0, 0.0, 1.0, 0.0],
+///     [0.0, 0.0, 0.0, 1.0],
+/// ]);
+/// assert_eq!(m.transform_point(Point3::new(1.0, 1.0, 1.0)).x(), 3.0);
+/// assert_eq!(m.transform_vector(Vec3::new(1.0, 1.0, 1.0)).x(), 1.0);
+/// ```
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Mat4 {
+    /// The rows of the matrix.
+    m: [[f64; 4]; 4],
+}
+
+impl Default for Mat4 {
+    fn default() -> Self {
+        Mat4::identity()
+    }
+}
+
+impl Mat4 {
+    /// Creates a matrix from its rows.
+    pub fn new(rows: [[f64; 4]; 4]) -> Mat4 {
+        Mat4 { m: rows }
+    }
+
+    /// The matrix leaving every point and vector unchanged.
+    pub fn identity() -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            row[i] = 1.0;
+        }
+        Mat4 { m }
+    }
+
+    /// The element at row `row` and column `col`.
+    pub fn get(&self, row: usize, col: usize) -> f64 {
+        self.m[row][col]
+    }
+
+    /// The matrix whose rows are the columns of this one.
+    pub fn transpose(&self) -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = self.m[j][i];
+            }
+        }
+        Mat4 { m }
+    }
+
+    /// The inverse of the matrix, or `None` if it is singular.
+    ///
+    /// Computed by Gauss-Jordan elimination with partial pivoting.
+    pub fn inverse(&self) -> Option<Mat4> {
+        let mut a = self.m;
+        let mut inv = Mat4::identity().m;
+
+        for col in 0..4 {
+            // Use the largest remaining value of the column as pivot, for stability
+            let pivot = (col..4).max_by(|&i, &j| a[i][col].abs().total_cmp(&a[j][col].abs()))?;
+            if a[pivot][col].abs() < 1e-12 {
+                return None;
+            }
+            a.swap(col, pivot);
+            inv.swap(col, pivot);
+
+            let scale = 1.0 / a[col][col];
+            for j in 0..4 {
+                a[col][j] *= scale;
+                inv[col][j] *= scale;
+            }
+
+            for row in 0..4 {
+                if row == col {
+                    continue;
+                }
+                let factor = a[row][col];
+                for j in 0..4 {
+                    a[row][j] -= factor * a[col][j];
+                    inv[row][j] -= factor * inv[col][j];
+                }
+            }
+        }
+
+        Some(Mat4 { m: inv })
+    }
+
+    /// Applies the matrix to a point, including the translation.
+    pub fn transform_point(&self, p: Point3) -> Point3 {
+        let m = &self.m;
+        let x = m[0][0] * p.x() + m[0][1] * p.y() + m[0][2] * p.z() + m[0][3];
+        let y = m[1][0] * p.x() + m[1][1] * p.y() + m[1][2] * p.z() + m[1][3];
+        let z = m[2][0] * p.x() + m[2][1] * p.y() + m[2][2] * p.z() + m[2][3];
+        let w = m[3][0] * p.x() + m[3][1] * p.y() + m[3][2] * p.z() + m[3][3];
+        if w == 1.0 {
+            Point3::new(x, y, z)
+        } else {
+            Point3::new(x / w, y / w, z / w)
+        }
+    }
+
+    /// Applies the matrix to a vector, ignoring the translation.
+    pub fn transform_vector(&self, v: Vec3) -> Vec3 {
+        let m = &self.m;
+        Vec3::new(
+            m[0][0] * v.x() + m[0][1] * v.y() + m[0][2] * v.z(),
+            m[1][0] * v.x() + m[1][1] * v.y() + m[1][2] * v.z(),
+            m[2][0] * v.x() + m[2][1] * v.y() + m[2][2] * v.z(),
+        )
+    }
+}
+
+impl Mul for Mat4 {
+    type Output = Mat4;
+
+    /// The matrix applying `rhs` first, then `self`.
+    fn mul(self, rhs: Mat4) -> Mat4 {
+        let mut m = [[0.0; 4]; 4];
+        for (i, row) in m.iter_mut().enumerate() {
+            for (j, value) in row.iter_mut().enumerate() {
+                *value = (0..4).map(|k| self.m[i][k] * rhs.m[k][j]).sum();
+            }
+        }
+        Mat4 { m }
+    }
+}
@@ -1,7 +1,11 @@
+use std::sync::Arc;
+
 use crate::color::Color;
+use crate::common::{self, PI};
 use crate::hittable::HitRecord;
 use crate::ray::Ray;
-use crate::{common, vec3};
+use crate::texture::{SolidColor, Texture};
+use crate::vec3::{self, Vec3};
 
 /// A record containing information about how a ray scatters after hitting a material.
 pub struct ScatterRecord {
@@ -11,12 +15,92 @@ pub struct ScatterRecord {
     pub scattered: Ray,
 }
 
+/// A direction picked by [`Material::sample`].
+#[derive(Debug, Clone, Copy)]
+pub struct BsdfSample {
+    /// The direction light comes from, of unit length and pointing away from the surface.
+    pub wi: Vec3,
+    /// The value of the BSDF for `wi`.
+    pub f: Color,
+    /// The density with which `wi` was picked, per unit of solid angle.
+    pub pdf: f64,
+    /// The factor `f × |cos θ| / pdf` by which the light coming from `wi` is
+    /// multiplied, `θ` being the angle between `wi` and the normal.
+    pub weight: Color,
+    /// Whether `wi` is the single direction of a delta lobe, like the reflection in
+    /// a mirror. Both `f` and `pdf` are then infinite in theory; the values given
+    /// only keep their ratio, and no other strategy could have picked `wi`.
+    pub is_delta: bool,
+}
+
+impl BsdfSample {
+    /// Creates a sample of a lobe spreading light over many directions.
+    fn new(rec: &HitRecord, wi: Vec3, f: Color, pdf: f64) -> BsdfSample {
+        let cosine = f64::abs(vec3::dot(wi, rec.normal));
+        BsdfSample {
+            wi,
+            f,
+            pdf,
+            weight: f * (cosine / pdf),
+            is_delta: false,
+        }
+    }
+
+    /// Creates a sample of a delta lobe, picked with the probability `probability`
+    /// among the lobes of the material and letting through `weight` of the light.
+    fn delta(rec: &HitRecord, wi: Vec3, weight: Color, probability: f64) -> BsdfSample {
+        let cosine = f64::abs(vec3::dot(wi, rec.normal));
+        BsdfSample {
+            wi,
+            f: weight * (probability / cosine),
+            pdf: probability,
+            weight,
+            is_delta: true,
+        }
+    }
+}
+
 /// A trait representing materials that can scatter light rays.
 ///
-/// Implementors define how rays interact with the material surface.
+/// Implementors describe how light is reflected by the surface with its BSDF,
+/// the bidirectional scattering distribution function: [`Material::eval`] gives
+/// how much of the light coming from a direction `wi` leaves towards a direction
+/// `wo`, [`Material::sample`] picks a direction `wi` for a given `wo` and
+/// [`Material::pdf`] gives the density with which it does so.
+///
+/// Directions are vectors of unit length pointing away from the hit point: `wo`
+/// towards the viewer, which is the side `rec.normal` faces, and `wi` towards
+/// where the light comes from. [`Material::sample`] must be implemented, it is what
+/// the renderer bounces rays with; the other methods default to a surface reflecting
+/// and emitting nothing.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use raytracer::{Color, Hittable, Lambertian, Material, Point3, Ray, Sphere, Vec3};
+///
+/// let matte = Lambertian::new(Color::new(0.5, 0.5, 0.5));
+/// let sphere = Sphere::new(Point3::new(0.0, 0.0, 0.0), 1.0, Arc::new(matte.clone()));
+/// // Looking straight down at the top of the sphere
+/// let rec = sphere.hit(&Ray::new(Point3::new(0.0, 2.0, 0.0), Vec3::new(0.0, -1.0, 0.0)), 0.001, f64::INFINITY).unwrap();
+/// let wo = Vec3::new(0.0, 1.0, 0.0);
+///
+/// // The same random numbers give the same direction
+/// let sample = matte.sample(&rec, wo, [0.3, 0.6, 0.9]).unwrap();
+/// let again = matte.sample(&rec, wo, [0.3, 0.6, 0.9]).unwrap();
+/// assert!((sample.wi - again.wi).near_zero());
+/// assert!(!sample.is_delta);
+/// assert!((sample.pdf - matte.pdf(&rec, wo, sample.wi)).abs() < 1e-12);
+/// ```
 pub trait Material: Send + Sync {
     /// Determines how a ray scatters after hitting a surface.
     ///
+    /// Picks a direction with [`Material::sample`] from the thread's random generator.
+    /// It is only a shortcut for callers: the renderer does not call it and uses
+    /// [`Material::sample`], [`Material::eval`] and [`Material::pdf`] instead, so
+    /// overriding it changes nothing in the images.
+    ///
     /// # Arguments
     ///
     /// * `r_in` - The incoming ray.
@@ -25,14 +109,88 @@ pub trait Material: Send + Sync {
     /// # Returns
     ///
     /// An `Option<ScatterRecord>` describing the scattered ray and attenuation, or `None` if absorption occurs.
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord>;
+    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+        let wo = -vec3::unit_vector(r_in.direction());
+        let sample = self.sample(rec, wo, random_numbers())?;
+        Some(ScatterRecord {
+            attenuation: sample.weight,
+            scattered: Ray::new(rec.p, sample.wi),
+        })
+    }
+
+    /// Returns the light emitted by the surface at the hit point, towards the origin of `r_in`.
+    ///
+    /// Most materials do not emit any light, so the default is black.
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    /// Returns whether the material emits any light, so that the objects using it
+    /// are worth sampling as lights.
+    fn is_emissive(&self) -> bool {
+        false
+    }
+
+    /// Returns the color of the surface at the hit point, whatever its lighting,
+    /// for the albedo [`Aov`](crate::Aov).
+    ///
+    /// The default is white, which suits surfaces that let all the light through, like glass.
+    fn albedo(&self, _rec: &HitRecord) -> Color {
+        Color::new(1.0, 1.0, 1.0)
+    }
+
+    /// Returns the value of the BSDF: the fraction of the light coming from `wi`
+    /// that leaves towards `wo`, per unit of solid angle and before the cosine
+    /// of the angle between `wi` and the normal is applied.
+    ///
+    /// Delta lobes, which only reflect into single directions, are 0 everywhere.
+    fn eval(&self, _rec: &HitRecord, _wo: Vec3, _wi: Vec3) -> Color {
+        Color::new(0.0, 0.0, 0.0)
+    }
+
+    /// Returns the density with which [`Material::sample`] picks `wi` for `wo`,
+    /// per unit of solid angle. Delta lobes are not counted.
+    fn pdf(&self, _rec: &HitRecord, _wo: Vec3, _wi: Vec3) -> f64 {
+        0.0
+    }
+
+    /// Picks the direction `wi` light comes from, for light leaving towards `wo`.
+    ///
+    /// The choice only depends on `u`, three independent numbers uniformly
+    /// distributed in `[0, 1)`, so the same numbers always give the same sample.
+    /// Returns `None` if the light is absorbed.
+    fn sample(&self, rec: &HitRecord, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample>;
+}
+
+/// Looks up the color of `texture` at the hit point `rec`.
+fn albedo(texture: &Arc<dyn Texture>, rec: &HitRecord) -> Color {
+    texture.get_color(rec.uv.x(), rec.uv.y(), &rec.p)
+}
+
+/// Draws the numbers given to [`Material::sample`] from the thread's random generator.
+pub(crate) fn random_numbers() -> [f64; 3] {
+    [common::random_double(), common::random_double(), common::random_double()]
 }
 
 /// A Lambertian (diffuse) material that scatters light in random directions.
-#[derive(Clone, Copy)]
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use raytracer::{CheckerTexture, Color, Lambertian};
+///
+/// let plain = Lambertian::new(Color::new(0.8, 0.3, 0.3));
+/// let tiles = Lambertian::from_texture(Arc::new(CheckerTexture::from_colors(
+///     0.5,
+///     Color::new(0.2, 0.3, 0.1),
+///     Color::new(0.9, 0.9, 0.9),
+/// )));
+/// ```
+#[derive(Clone)]
 pub struct Lambertian {
-    /// The albedo (base color) of the material.
-    albedo: Color,
+    /// The albedo (base color) of the material, which may vary over the surface.
+    albedo: Arc<dyn Texture>,
 }
 
 impl Lambertian {
@@ -46,31 +204,64 @@ impl Lambertian {
     ///
     /// A new `Lambertian` material instance.
     pub fn new(a: Color) -> Lambertian {
-        Lambertian { albedo: a }
+        Lambertian::from_texture(Arc::new(SolidColor::new(a)))
+    }
+
+    /// Creates a new Lambertian material whose albedo is given by a texture.
+    ///
+    /// # Arguments
+    ///
+    /// * `tex` - The texture giving the albedo at each point of the surface.
+    ///
+    /// # Returns
+    ///
+    /// A new `Lambertian` material instance.
+    pub fn from_texture(tex: Arc<dyn Texture>) -> Lambertian {
+        Lambertian { albedo: tex }
     }
 }
 
 impl Material for Lambertian {
-    fn scatter(&self, _r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
-        let mut scatter_direction = rec.normal + vec3::random_unit_vector();
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        albedo(&self.albedo, rec)
+    }
 
-        // Catch degenerate scatter direction
-        if scatter_direction.near_zero() {
-            scatter_direction = rec.normal;
+    /// Light is spread evenly in every direction above the surface.
+    fn eval(&self, rec: &HitRecord, _wo: Vec3, wi: Vec3) -> Color {
+        if vec3::dot(wi, rec.normal) > 0.0 {
+            albedo(&self.albedo, rec) / PI
+        } else {
+            Color::new(0.0, 0.0, 0.0)
         }
+    }
 
-        Some(ScatterRecord {
-            attenuation: self.albedo,
-            scattered: Ray::new(rec.p, scatter_direction),
-        })
+    /// Directions are picked with a density proportional to their cosine with the normal.
+    fn pdf(&self, rec: &HitRecord, _wo: Vec3, wi: Vec3) -> f64 {
+        f64::max(0.0, vec3::dot(wi, rec.normal)) / PI
+    }
+
+    fn sample(&self, rec: &HitRecord, _wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        // Points spread evenly on the unit disk, projected up onto the hemisphere
+        let r = u[0].sqrt();
+        let phi = 2.0 * PI * u[1];
+        let z = f64::sqrt(1.0 - u[0]);
+        let (tangent, bitangent) = vec3::orthonormal_basis(rec.normal);
+        let wi = vec3::unit_vector(r * phi.cos() * tangent + r * phi.sin() * bitangent + z * rec.normal);
+
+        let pdf = self.pdf(rec, Vec3::default(), wi);
+        // Catch degenerate directions, grazing the surface
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample::new(rec, wi, albedo(&self.albedo, rec) / PI, pdf))
     }
 }
 
 /// A metallic (reflective) material with optional fuzziness.
-#[derive(Clone, Copy)]
+#[derive(Clone)]
 pub struct Metal {
-    /// The albedo (reflective color) of the metal.
-    albedo: Color,
+    /// The albedo (reflective color) of the metal, which may vary over the surface.
+    albedo: Arc<dyn Texture>,
     /// The fuzziness factor (0 = perfect mirror, 1 = maximum blur).
     fuzz: f64,
 }
@@ -87,26 +278,93 @@ impl Metal {
     ///
     /// A new `Metal` material instance.
     pub fn new(a: Color, f: f64) -> Metal {
+        Metal::from_texture(Arc::new(SolidColor::new(a)), f)
+    }
+
+    /// Creates a new metallic material whose albedo is given by a texture.
+    ///
+    /// # Arguments
+    ///
+    /// * `tex` - The texture giving the albedo at each point of the surface.
+    /// * `f` - The fuzziness of reflections (clamped between 0 and 1).
+    ///
+    /// # Returns
+    ///
+    /// A new `Metal` material instance.
+    pub fn from_texture(tex: Arc<dyn Texture>, f: f64) -> Metal {
         Metal {
-            albedo: a,
+            albedo: tex,
             fuzz: if f < 1.0 { f } else { 1.0 },
         }
     }
+
+    /// The direction of the perfect reflection of `wo`.
+    fn reflected(rec: &HitRecord, wo: Vec3) -> Vec3 {
+        vec3::reflect(-wo, rec.normal)
+    }
 }
 
 impl Material for Metal {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
-        let reflected = vec3::reflect(vec3::unit_vector(r_in.direction()), rec.normal);
-        let scattered = Ray::new(rec.p, reflected + self.fuzz * vec3::random_in_unit_sphere());
-
-        if vec3::dot(scattered.direction(), rec.normal) > 0.0 {
-            Some(ScatterRecord {
-                attenuation: self.albedo,
-                scattered,
-            })
-        } else {
-            None
+    fn albedo(&self, rec: &HitRecord) -> Color {
+        albedo(&self.albedo, rec)
+    }
+
+    fn eval(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> Color {
+        let cosine = vec3::dot(wi, rec.normal);
+        if cosine <= 0.0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        // Every direction picked lets the albedo through: f × cos θ / pdf = albedo
+        albedo(&self.albedo, rec) * (self.pdf(rec, wo, wi) / cosine)
+    }
+
+    /// The reflection is blurred by moving it to a random point of a ball of radius
+    /// `fuzz`: the density of a direction is the part of the ball seen along it.
+    fn pdf(&self, rec: &HitRecord, wo: Vec3, wi: Vec3) -> f64 {
+        if self.fuzz == 0.0 || vec3::dot(wi, rec.normal) <= 0.0 {
+            return 0.0;
+        }
+        // The ray t × wi enters the ball around the reflection at t1 and leaves at t2
+        let cos_alpha = vec3::dot(wi, Self::reflected(rec, wo));
+        let discriminant = self.fuzz * self.fuzz - (1.0 - cos_alpha * cos_alpha);
+        if discriminant <= 0.0 {
+            return 0.0;
         }
+        let t1 = f64::max(0.0, cos_alpha - discriminant.sqrt());
+        let t2 = cos_alpha + discriminant.sqrt();
+        if t2 <= 0.0 {
+            return 0.0;
+        }
+        // The volume of the cone slice, ∫ t² dt, over the volume of the ball
+        (t2.powi(3) - t1.powi(3)) / (4.0 * PI * self.fuzz.powi(3))
+    }
+
+    fn sample(&self, rec: &HitRecord, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
+        let reflected = Self::reflected(rec, wo);
+        if self.fuzz == 0.0 {
+            if vec3::dot(reflected, rec.normal) <= 0.0 {
+                return None;
+            }
+            return Some(BsdfSample::delta(rec, reflected, albedo(&self.albedo, rec), 1.0));
+        }
+
+        // A point spread evenly in the ball, from its radius and a direction
+        let radius = self.fuzz * u[0].cbrt();
+        let z = 1.0 - 2.0 * u[1];
+        let phi = 2.0 * PI * u[2];
+        let s = f64::sqrt(f64::max(0.0, 1.0 - z * z));
+        let offset = radius * Vec3::new(s * phi.cos(), s * phi.sin(), z);
+
+        let wi = vec3::unit_vector(reflected + offset);
+        // Light scattered below the surface is absorbed
+        if vec3::dot(wi, rec.normal) <= 0.0 {
+            return None;
+        }
+        let pdf = self.pdf(rec, wo, wi);
+        if pdf <= 0.0 {
+            return None;
+        }
+        Some(BsdfSample::new(rec, wi, self.eval(rec, wo, wi), pdf))
     }
 }
 
@@ -151,25 +409,69 @@ impl Dielectric {
 }
 
 impl Material for Dielectric {
-    fn scatter(&self, r_in: &Ray, rec: &HitRecord) -> Option<ScatterRecord> {
+    /// Light is either reflected or refracted, both delta lobes picked with the
+    /// probability given by the Fresnel reflectance.
+    fn sample(&self, rec: &HitRecord, wo: Vec3, u: [f64; 3]) -> Option<BsdfSample> {
         let refraction_ratio = if rec.front_face { 1.0 / self.ir } else { self.ir };
 
-        let unit_direction = vec3::unit_vector(r_in.direction());
-        let cos_theta = f64::min(vec3::dot(-unit_direction, rec.normal), 1.0);
+        let unit_direction = -wo;
+        let cos_theta = f64::min(vec3::dot(wo, rec.normal), 1.0);
         let sin_theta = f64::sqrt(1.0 - cos_theta * cos_theta);
 
         let cannot_refract = refraction_ratio * sin_theta > 1.0;
-        let direction = if cannot_refract
-            || Self::reflectance(cos_theta, refraction_ratio) > common::random_double()
-        {
-            vec3::reflect(unit_direction, rec.normal)
+        let reflectance = if cannot_refract {
+            1.0
         } else {
-            vec3::refract(unit_direction, rec.normal, refraction_ratio)
+            Self::reflectance(cos_theta, refraction_ratio)
         };
 
-        Some(ScatterRecord {
-            attenuation: Color::new(1.0, 1.0, 1.0),
-            scattered: Ray::new(rec.p, direction),
-        })
+        let white = Color::new(1.0, 1.0, 1.0);
+        if u[0] < reflectance {
+            let wi = vec3::reflect(unit_direction, rec.normal);
+            Some(BsdfSample::delta(rec, wi, white, reflectance))
+        } else {
+            let wi = vec3::unit_vector(vec3::refract(unit_direction, rec.normal, refraction_ratio));
+            Some(BsdfSample::delta(rec, wi, white, 1.0 - reflectance))
+        }
+    }
+}
+
+/// A material emitting light of a uniform color from both sides, and reflecting none.
+///
+/// Colors above 1 make brighter lights: a small light has to be very bright to
+/// light a whole room.
+#[derive(Clone, Copy)]
+pub struct DiffuseLight {
+    /// The emitted radiance.
+    emit: Color,
+}
+
+impl DiffuseLight {
+    /// Creates a new light material.
+    ///
+    /// # Arguments
+    ///
+    /// * `emit` - The color and intensity of the emitted light.
+    ///
+    /// # Returns
+    ///
+    /// A new `DiffuseLight` material instance.
+    pub fn new(emit: Color) -> DiffuseLight {
+        DiffuseLight { emit }
+    }
+}
+
+impl Material for DiffuseLight {
+    fn emitted(&self, _r_in: &Ray, _rec: &HitRecord) -> Color {
+        self.emit
+    }
+
+    fn is_emissive(&self) -> bool {
+        !self.emit.near_zero()
+    }
+
+    /// Lights absorb all the light reaching them.
+    fn sample(&self, _rec: &HitRecord, _wo: Vec3, _u: [f64; 3]) -> Option<BsdfSample> {
+        None
     }
 }
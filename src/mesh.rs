@@ -0,0 +1,206 @@
+use crate::vec3::*;
+use crate::vec2::UV;
+use crate::material::*;
+use std::sync::Arc;
+use crate::hittable::{HitRecord, Hittable};
+use crate::aabb::Aabb;
+use crate::bvh::FlatBvh;
+use crate::ray::Ray;
+use crate::triangle;
+
+/// Faces of a mesh sharing the same material.
+pub struct FaceGroup {
+    /// Indices of the three vertices of each face, counter-clockwise seen from the front.
+    pub faces: Vec<[u32; 3]>,
+    /// The material applied to every face of the group.
+    pub material: Arc<dyn Material>,
+}
+
+impl FaceGroup {
+    /// Creates a group of faces using the material `material`.
+    pub fn new(faces: Vec<[u32; 3]>, material: Arc<dyn Material>) -> Self {
+        FaceGroup { faces, material }
+    }
+}
+
+/// A triangle mesh whose faces share a single array of vertices.
+///
+/// Faces only store the indices of their vertices and of their material, which
+/// makes a mesh far lighter than as many [`Triangle`](crate::Triangle)s. Normals
+/// and texture coordinates, when attached, are given per vertex and follow the
+/// same indices as the positions. The faces are kept in a bounding volume
+/// hierarchy of their own, so a mesh can be added to a
+/// [`HittableList`](crate::HittableList) like any other object.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use raytracer::{Color, Hittable, Lambertian, Point3, Ray, TriangleMesh, Vec3};
+///
+/// let material = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3)));
+/// // A unit square in the xy plane, made of two triangles
+/// let positions = vec![
+///     Point3::new(0.0, 0.0, 0.0),
+///     Point3::new(1.0, 0.0, 0.0),
+///     Point3::new(1.0, 1.0, 0.0),
+///     Point3::new(0.0, 1.0, 0.0),
+/// ];
+/// let square = TriangleMesh::new(positions, vec![[0, 1, 2], [0, 2, 3]], material)
+///     .with_normals(vec![Vec3::new(0.0, 0.0, 1.0); 4]);
+///
+/// let ray = Ray::new(Point3::new(0.25, 0.75, 1.0), Vec3::new(0.0, 0.0, -1.0));
+/// let rec = square.hit(&ray, 0.001, f64::INFINITY).unwrap();
+/// assert!((rec.t - 1.0).abs() < 1e-9);
+/// assert!(rec.front_face);
+/// ```
+pub struct TriangleMesh {
+    positions: Vec<Point3>,
+    /// Unit normal at each vertex, empty for a flat-shaded mesh.
+    normals: Vec<Vec3>,
+    /// Texture coordinates at each vertex, empty if the mesh has none.
+    uvs: Vec<UV>,
+    /// Vertex indices of each face, sorted in the order of the hierarchy.
+    faces: Vec<[u32; 3]>,
+    /// Index in `materials` of the material of each face.
+    face_materials: Vec<u32>,
+    materials: Vec<Arc<dyn Material>>,
+    bvh: FlatBvh,
+}
+
+impl TriangleMesh {
+    /// Creates a mesh whose faces all use the same material.
+    ///
+    /// # Arguments
+    /// * `positions` - The vertices shared by the faces.
+    /// * `faces` - Indices in `positions` of the three vertices of each face,
+    ///   counter-clockwise seen from the front.
+    /// * `mat` - The material to apply to the mesh.
+    ///
+    /// # Panics
+    /// If a face refers to a vertex that does not exist.
+    pub fn new(positions: Vec<Point3>, faces: Vec<[u32; 3]>, mat: Arc<dyn Material>) -> Self {
+        Self::with_groups(positions, vec![FaceGroup::new(faces, mat)])
+    }
+
+    /// Creates a mesh made of several groups of faces, each with its own material.
+    ///
+    /// # Panics
+    /// If a face refers to a vertex that does not exist.
+    pub fn with_groups(positions: Vec<Point3>, groups: Vec<FaceGroup>) -> Self {
+        let mut faces = Vec::new();
+        let mut face_materials = Vec::new();
+        let mut materials = Vec::with_capacity(groups.len());
+        for (i, group) in groups.into_iter().enumerate() {
+            face_materials.resize(face_materials.len() + group.faces.len(), i as u32);
+            faces.extend(group.faces);
+            materials.push(group.material);
+        }
+
+        if let Some(face) = faces.iter().find(|face| face.iter().any(|&v| v as usize >= positions.len())) {
+            panic!("face {:?} refers to a vertex out of the {} of the mesh", face, positions.len());
+        }
+
+        let boxes: Vec<Aabb> = faces
+            .iter()
+            .map(|face| Aabb::from_points(&face.map(|v| positions[v as usize])))
+            .collect();
+        let (bvh, order) = FlatBvh::build(&boxes);
+
+        TriangleMesh {
+            faces: order.iter().map(|&i| faces[i]).collect(),
+            face_materials: order.iter().map(|&i| face_materials[i]).collect(),
+            positions,
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            materials,
+            bvh,
+        }
+    }
+
+    /// Attaches a normal to each vertex, to shade the mesh smoothly.
+    ///
+    /// # Panics
+    /// If there is not exactly one normal per vertex.
+    pub fn with_normals(mut self, normals: Vec<Vec3>) -> Self {
+        assert_eq!(normals.len(), self.positions.len(), "a mesh needs one normal per vertex");
+        self.normals = normals.into_iter().map(unit_vector).collect();
+        self
+    }
+
+    /// Attaches texture coordinates to each vertex.
+    ///
+    /// # Panics
+    /// If there are not exactly one pair of coordinates per vertex.
+    pub fn with_uvs(mut self, uvs: Vec<UV>) -> Self {
+        assert_eq!(uvs.len(), self.positions.len(), "a mesh needs one uv per vertex");
+        self.uvs = uvs;
+        self
+    }
+
+    /// Applies `mat` to every face, in place of the materials of the groups.
+    pub fn with_material(mut self, mat: Arc<dyn Material>) -> Self {
+        self.materials = vec![mat];
+        self.face_materials.fill(0);
+        self
+    }
+
+    /// The number of vertices shared by the faces.
+    pub fn vertex_count(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// The number of triangles of the mesh.
+    pub fn face_count(&self) -> usize {
+        self.faces.len()
+    }
+
+    /// Tests the ray against the `i`-th face, in the order of the hierarchy.
+    fn hit_face(&self, i: usize, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let [i0, i1, i2] = self.faces[i].map(|v| v as usize);
+        let v0 = self.positions[i0];
+        let e1 = self.positions[i1] - v0;
+        let e2 = self.positions[i2] - v0;
+        let (t, b1, b2) = triangle::intersect(v0, e1, e2, ray, t_min, t_max)?;
+
+        let mut rec = HitRecord {
+            t,
+            p: ray.at(t),
+            mat: self.materials[self.face_materials[i] as usize].clone(),
+            normal: Default::default(),
+            front_face: Default::default(),
+            uv: if self.uvs.is_empty() {
+                UV::new(b1, b2)
+            } else {
+                triangle::interpolate_uv([self.uvs[i0], self.uvs[i1], self.uvs[i2]], b1, b2)
+            },
+        };
+
+        // Same as a lone triangle: the geometric normal decides the face
+        rec.set_face_normal(ray, unit_vector(cross(e1, e2)));
+        if !self.normals.is_empty() {
+            let (n0, n1, n2) = (self.normals[i0], self.normals[i1], self.normals[i2]);
+            let shading_normal = unit_vector((1.0 - b1 - b2) * n0 + b1 * n1 + b2 * n2);
+            rec.normal = if rec.front_face {
+                shading_normal
+            } else {
+                -shading_normal
+            };
+        }
+
+        Some(rec)
+    }
+}
+
+impl Hittable for TriangleMesh {
+    /// Finds the closest face hit by the ray, through the hierarchy over the faces.
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.bvh.hit(ray, t_min, t_max, |i, ray, t_min, t_max| {
+            self.hit_face(i, ray, t_min, t_max)
+        })
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bvh.bounding_box()
+    }
+}
@@ -0,0 +1,214 @@
+use std::fmt;
+use std::path::PathBuf;
+
+use crate::texture::ImageError;
+
+/// Where a problem was found in a scene file.
+///
+/// `path` names the offending value the way it would be written in code, for
+/// example `objects[3].material`. `line` and `column` are 1-based and point at
+/// the value itself, or at the enclosing object when a field is missing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Location {
+    /// JSON path of the value, e.g. `camera.vup` or `objects[0].radius`.
+    pub path: String,
+    /// Line of the value in the source, starting at 1.
+    pub line: usize,
+    /// Column of the value in the source, starting at 1.
+    pub column: usize,
+}
+
+impl fmt::Display for Location {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} (line {}, column {})", self.path, self.line, self.column)
+    }
+}
+
+/// Error returned when a scene file cannot be loaded.
+///
+/// Every variant but [`ParseError::Io`] tells where the problem is, so a hand-edited
+/// scene can be fixed without guessing:
+///
+/// ```
+/// use raytracer::parsing::{parse_scene, ParseError};
+///
+/// let err = parse_scene(r#"{
+///     "camera": { "lookfrom": [2, 1, 2], "lookat": [0, 0, 0], "vfov": 40 },
+///     "objects": [
+///         { "type": "sphere", "center": [0, 0, 0], "radius": 1, "material": "gold" }
+///     ]
+/// }"#).err().unwrap();
+///
+/// match err {
+///     ParseError::UnknownMaterial { location, name } => {
+///         assert_eq!(location.path, "objects[0].material");
+///         assert_eq!(location.line, 4);
+///         assert_eq!(name, "gold");
+///     }
+///     _ => panic!("unexpected error: {}", err),
+/// }
+/// ```
+#[derive(Debug)]
+pub enum ParseError {
+    /// The scene file could not be read.
+    Io(std::io::Error),
+    /// The scene file is not valid JSON.
+    Syntax {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    /// A required field is absent. The location points at the enclosing object.
+    MissingField { location: Location },
+    /// A value does not have the expected JSON type.
+    WrongType {
+        location: Location,
+        expected: &'static str,
+    },
+    /// The `type` of a material or an object is not one the parser knows.
+    UnknownType {
+        location: Location,
+        found: String,
+        expected: &'static str,
+    },
+    /// An object references a material that is not declared in `materials`.
+    UnknownMaterial { location: Location, name: String },
+    /// An instance references a prototype that is not declared in `prototypes`.
+    UnknownPrototype { location: Location, name: String },
+    /// A value has the right type but is out of range, e.g. a negative radius.
+    InvalidValue {
+        location: Location,
+        expected: &'static str,
+    },
+    /// A mesh referenced by the scene could not be loaded.
+    Mesh { location: Location, error: ObjError },
+    /// An image texture referenced by the scene could not be loaded.
+    Image { location: Location, error: ImageError },
+}
+
+impl ParseError {
+    /// Returns the location of the error in the source, if it has one.
+    pub fn location(&self) -> Option<&Location> {
+        match self {
+            ParseError::Io(_) | ParseError::Syntax { .. } => None,
+            ParseError::MissingField { location }
+            | ParseError::WrongType { location, .. }
+            | ParseError::UnknownType { location, .. }
+            | ParseError::UnknownMaterial { location, .. }
+            | ParseError::UnknownPrototype { location, .. }
+            | ParseError::InvalidValue { location, .. }
+            | ParseError::Mesh { location, .. }
+            | ParseError::Image { location, .. } => Some(location),
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::Io(err) => write!(f, "cannot read scene: {}", err),
+            ParseError::Syntax {
+                line,
+                column,
+                message,
+            } => write!(f, "line {}, column {}: {}", line, column, message),
+            ParseError::MissingField { location } => {
+                write!(f, "{}: missing required field", location)
+            }
+            ParseError::WrongType { location, expected } => {
+                write!(f, "{}: expected {}", location, expected)
+            }
+            ParseError::UnknownType {
+                location,
+                found,
+                expected,
+            } => write!(f, "{}: unknown type \"{}\", expected {}", location, found, expected),
+            ParseError::UnknownMaterial { location, name } => {
+                write!(f, "{}: unknown material \"{}\"", location, name)
+            }
+            ParseError::UnknownPrototype { location, name } => {
+                write!(f, "{}: unknown prototype \"{}\"", location, name)
+            }
+            ParseError::InvalidValue { location, expected } => {
+                write!(f, "{}: expected {}", location, expected)
+            }
+            ParseError::Mesh { location, error } => write!(f, "{}: {}", location, error),
+            ParseError::Image { location, error } => write!(f, "{}: {}", location, error),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Io(err) => Some(err),
+            ParseError::Mesh { error, .. } => Some(error),
+            ParseError::Image { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<std::io::Error> for ParseError {
+    fn from(err: std::io::Error) -> Self {
+        ParseError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for ParseError {
+    fn from(err: serde_json::Error) -> Self {
+        if err.is_io() {
+            return ParseError::Io(err.into());
+        }
+        // serde_json appends the position to its message, it is reported separately
+        let message = err.to_string();
+        let suffix = format!(" at line {} column {}", err.line(), err.column());
+        ParseError::Syntax {
+            line: err.line(),
+            column: err.column(),
+            message: message.strip_suffix(&suffix).unwrap_or(&message).to_string(),
+        }
+    }
+}
+
+/// Error returned when a Wavefront OBJ file or one of its MTL libraries cannot be loaded.
+#[derive(Debug)]
+pub enum ObjError {
+    /// A file could not be read.
+    Io { path: PathBuf, error: std::io::Error },
+    /// A statement is malformed, e.g. a face referring to a vertex that does not exist.
+    ///
+    /// `file` is `None` when the OBJ source was given as a string.
+    Syntax {
+        file: Option<PathBuf>,
+        line: usize,
+        message: String,
+    },
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Io { path, error } => write!(f, "cannot read {}: {}", path.display(), error),
+            ObjError::Syntax {
+                file: Some(file),
+                line,
+                message,
+            } => write!(f, "{}, line {}: {}", file.display(), line, message),
+            ObjError::Syntax {
+                file: None,
+                line,
+                message,
+            } => write!(f, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ObjError::Io { error, .. } => Some(error),
+            ObjError::Syntax { .. } => None,
+        }
+    }
+}
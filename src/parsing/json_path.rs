@@ -0,0 +1,215 @@
+use std::fmt;
+
+/// One step of a [`JsonPath`]: a key of an object or an index in an array.
+#[derive(Debug, Clone)]
+enum Segment {
+    Key(String),
+    Index(usize),
+}
+
+/// Path from the root of a JSON document to one of its values.
+///
+/// `serde_json::Value` does not remember where it was parsed from, so the path is
+/// used to find the value again in the source text when an error is reported.
+#[derive(Debug, Clone, Default)]
+pub struct JsonPath {
+    segments: Vec<Segment>,
+}
+
+impl JsonPath {
+    /// The path of the document root.
+    pub fn root() -> JsonPath {
+        Default::default()
+    }
+
+    /// The path of the field `key` of the object at this path.
+    pub fn key(&self, key: &str) -> JsonPath {
+        let mut path = self.clone();
+        path.segments.push(Segment::Key(key.to_string()));
+        path
+    }
+
+    /// The path of the element `index` of the array at this path.
+    pub fn index(&self, index: usize) -> JsonPath {
+        let mut path = self.clone();
+        path.segments.push(Segment::Index(index));
+        path
+    }
+
+    /// Finds the value at this path in `src` and returns its 1-based line and column.
+    ///
+    /// Walks as deep as the source allows: if a segment cannot be found, the position
+    /// of the deepest value reached is returned. `src` is expected to be valid JSON.
+    pub fn locate(&self, src: &str) -> (usize, usize) {
+        let mut scanner = Scanner { src: src.as_bytes(), pos: 0 };
+        scanner.skip_whitespace();
+
+        for segment in &self.segments {
+            let start = scanner.pos;
+            let found = match segment {
+                Segment::Key(key) => scanner.enter_key(key),
+                Segment::Index(index) => scanner.enter_index(*index),
+            };
+            if !found {
+                scanner.pos = start;
+                break;
+            }
+        }
+
+        line_column(src, scanner.pos)
+    }
+}
+
+impl fmt::Display for JsonPath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.segments.is_empty() {
+            return write!(f, "<root>");
+        }
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                // Keys that would make the path ambiguous are written quoted
+                Segment::Key(key) if !is_identifier(key) => write!(f, "[{:?}]", key)?,
+                Segment::Key(key) if i == 0 => write!(f, "{}", key)?,
+                Segment::Key(key) => write!(f, ".{}", key)?,
+                Segment::Index(index) => write!(f, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+fn is_identifier(key: &str) -> bool {
+    !key.is_empty() && key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Converts a byte offset into a 1-based line and column.
+fn line_column(src: &str, offset: usize) -> (usize, usize) {
+    let before = &src[..offset.min(src.len())];
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |i| i + 1);
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
+/// Minimal JSON scanner able to skip values and step into objects and arrays.
+struct Scanner<'a> {
+    src: &'a [u8],
+    pos: usize,
+}
+
+impl Scanner<'_> {
+    fn peek(&self) -> Option<u8> {
+        self.src.get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    /// Consumes `byte` (after whitespace) if it is the next character.
+    fn eat(&mut self, byte: u8) -> bool {
+        self.skip_whitespace();
+        if self.peek() == Some(byte) {
+            self.pos += 1;
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Consumes a string literal and returns its decoded content.
+    fn string(&mut self) -> Option<String> {
+        self.skip_whitespace();
+        let start = self.pos;
+        if self.peek() != Some(b'"') {
+            return None;
+        }
+        self.pos += 1;
+        while let Some(c) = self.peek() {
+            self.pos += 1;
+            match c {
+                b'\\' => self.pos += 1,
+                b'"' => break,
+                _ => {}
+            }
+        }
+        let end = self.pos.min(self.src.len());
+        let raw = std::str::from_utf8(&self.src[start..end]).ok()?;
+        serde_json::from_str(raw).ok()
+    }
+
+    /// Consumes one value of any kind.
+    fn skip_value(&mut self) {
+        self.skip_whitespace();
+        match self.peek() {
+            Some(b'"') => {
+                self.string();
+            }
+            Some(open @ (b'{' | b'[')) => {
+                let close = if open == b'{' { b'}' } else { b']' };
+                self.pos += 1;
+                loop {
+                    self.skip_whitespace();
+                    match self.peek() {
+                        None => return,
+                        Some(c) if c == close => {
+                            self.pos += 1;
+                            return;
+                        }
+                        Some(b',' | b':') => self.pos += 1,
+                        Some(_) => self.skip_value(),
+                    }
+                }
+            }
+            Some(_) => {
+                while !matches!(
+                    self.peek(),
+                    None | Some(b',' | b'}' | b']' | b' ' | b'\t' | b'\n' | b'\r')
+                ) {
+                    self.pos += 1;
+                }
+            }
+            None => {}
+        }
+    }
+
+    /// Moves to the value of `key` in the object starting at the current position.
+    fn enter_key(&mut self, key: &str) -> bool {
+        if !self.eat(b'{') {
+            return false;
+        }
+        loop {
+            let Some(name) = self.string() else {
+                return false;
+            };
+            if !self.eat(b':') {
+                return false;
+            }
+            self.skip_whitespace();
+            if name == key {
+                return true;
+            }
+            self.skip_value();
+            if !self.eat(b',') {
+                return false;
+            }
+        }
+    }
+
+    /// Moves to the element `index` of the array starting at the current position.
+    fn enter_index(&mut self, index: usize) -> bool {
+        if !self.eat(b'[') {
+            return false;
+        }
+        for _ in 0..index {
+            self.skip_value();
+            if !self.eat(b',') {
+                return false;
+            }
+        }
+        self.skip_whitespace();
+        !matches!(self.peek(), None | Some(b']'))
+    }
+}
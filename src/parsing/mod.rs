@@ -1 +1,42 @@
-mod parser;
\ No newline at end of file
+//! Loading of scenes described in JSON.
+//!
+//! A scene file declares a camera, a set of named materials and a list of
+//! objects referencing those materials by name:
+//!
+//! ```json
+//! {
+//!     "camera": {
+//!         "lookfrom": [5.0, 3.0, 5.0],
+//!         "lookat": [0.0, 0.5, 0.0],
+//!         "vup": [0.0, 1.0, 0.0],
+//!         "vfov": 30.0,
+//!         "aspect_ratio": 1.5,
+//!         "aperture": 0.1,
+//!         "focus_dist": 10.0
+//!     },
+//!     "materials": {
+//!         "ground": { "type": "lambertian", "albedo": [0.5, 0.5, 0.5] },
+//!         "gold": { "type": "metal", "albedo": [0.8, 0.6, 0.2], "fuzz": 0.1 },
+//!         "glass": { "type": "dielectric", "ir": 1.5 }
+//!     },
+//!     "objects": [
+//!         { "type": "sphere", "center": [0.0, -1000.0, 0.0], "radius": 1000.0, "material": "ground" },
+//!         { "type": "cube", "center": [1.0, 0.5, 0.0], "size": 1.0, "material": "gold" },
+//!         { "type": "sphere", "center": [-1.0, 0.5, 0.0], "radius": 0.5, "material": "glass" }
+//!     ]
+//! }
+//! ```
+//!
+//! Triangle meshes are loaded from Wavefront OBJ files, along with their MTL
+//! material libraries, either on their own with [`load_obj`] or from a scene
+//! with an object like `{ "type": "mesh", "path": "models/teapot.obj" }`.
+
+mod error;
+mod json_path;
+mod mtl;
+mod obj;
+mod parser;
+
+pub use error::{Location, ObjError, ParseError};
+pub use obj::{load_obj, parse_obj};
+pub use parser::{load_scene, parse_scene};
@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::path::Path;
+use std::str::SplitWhitespace;
+use std::sync::Arc;
+
+use super::error::ObjError;
+use super::obj::{self, DEFAULT_ALBEDO};
+use crate::color::Color;
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+
+/// Index of refraction of transparent materials that do not give `Ni`, the one of glass.
+const DEFAULT_IR: f64 = 1.5;
+
+/// The statements of a material of an MTL library used by the renderer.
+struct MtlEntry {
+    /// Diffuse color.
+    kd: Color,
+    /// Specular color.
+    ks: Color,
+    /// Emitted color.
+    ke: Color,
+    /// Specular exponent, from 0 to 1000: the higher, the sharper the reflections.
+    ns: f64,
+    /// Index of refraction.
+    ni: Option<f64>,
+    /// Opacity, 1 for an opaque material.
+    d: f64,
+    /// Illumination model.
+    illum: u32,
+}
+
+impl Default for MtlEntry {
+    fn default() -> Self {
+        MtlEntry {
+            kd: Color::new(DEFAULT_ALBEDO, DEFAULT_ALBEDO, DEFAULT_ALBEDO),
+            ks: Color::default(),
+            ke: Color::default(),
+            ns: 0.0,
+            ni: None,
+            d: 1.0,
+            illum: 2,
+        }
+    }
+}
+
+impl MtlEntry {
+    /// Maps the material onto the closest one the renderer has.
+    fn material(&self) -> Arc<dyn Material> {
+        let transparent = self.d < 1.0 || matches!(self.illum, 4 | 6 | 7 | 9);
+        let reflective = matches!(self.illum, 3 | 5 | 8);
+
+        if !self.ke.near_zero() {
+            Arc::new(DiffuseLight::new(self.ke))
+        } else if transparent {
+            // An index of 1 would make the surface invisible, it means "not given"
+            let ir = self.ni.filter(|&ni| ni > 1.0).unwrap_or(DEFAULT_IR);
+            Arc::new(Dielectric::new(ir))
+        } else if reflective {
+            let albedo = if self.ks.near_zero() { self.kd } else { self.ks };
+            // Same width of the highlight as a Blinn-Phong lobe of exponent Ns
+            let fuzz = (2.0 / (self.ns.max(0.0) + 2.0)).sqrt();
+            Arc::new(Metal::new(albedo, fuzz))
+        } else {
+            Arc::new(Lambertian::new(self.kd))
+        }
+    }
+}
+
+/// Reads the materials of the MTL library at `path`, by name.
+pub(super) fn load_mtl(path: &Path) -> Result<HashMap<String, Arc<dyn Material>>, ObjError> {
+    let src = obj::read(path)?;
+    let error = |line: usize, message: String| ObjError::Syntax {
+        file: Some(path.to_path_buf()),
+        line,
+        message,
+    };
+
+    let mut entries: Vec<(String, MtlEntry)> = Vec::new();
+    for (i, line) in src.lines().enumerate() {
+        let line_number = i + 1;
+        let line = line.split('#').next().unwrap_or_default();
+        let mut tokens = line.split_whitespace();
+        let Some(keyword) = tokens.next() else {
+            continue;
+        };
+
+        if keyword == "newmtl" {
+            let name = line.trim_start()[keyword.len()..].trim();
+            entries.push((name.to_string(), MtlEntry::default()));
+            continue;
+        }
+
+        let Some((_, entry)) = entries.last_mut() else {
+            return Err(error(line_number, format!("\"{}\" before any newmtl", keyword)));
+        };
+        let color = |tokens: SplitWhitespace| {
+            // A single value is a gray
+            let gray = tokens.clone().count() == 1;
+            let [r, g, b] = obj::numbers(tokens, if gray { 1 } else { 3 })
+                .map_err(|m| error(line_number, m))?;
+            Ok(if gray { Color::new(r, r, r) } else { Color::new(r, g, b) })
+        };
+        let number = |tokens| {
+            obj::numbers::<1>(tokens, 1)
+                .map(|[x]| x)
+                .map_err(|m| error(line_number, m))
+        };
+
+        match keyword {
+            "Kd" => entry.kd = color(tokens)?,
+            "Ks" => entry.ks = color(tokens)?,
+            "Ke" => entry.ke = color(tokens)?,
+            "Ns" => entry.ns = number(tokens)?,
+            "Ni" => entry.ni = Some(number(tokens)?),
+            "d" => entry.d = number(tokens)?,
+            "Tr" => entry.d = 1.0 - number(tokens)?,
+            "illum" => {
+                let illum = number(tokens)?;
+                if !(0.0..=10.0).contains(&illum) || illum.fract() != 0.0 {
+                    return Err(error(line_number, "expected an illumination model from 0 to 10".into()));
+                }
+                entry.illum = illum as u32;
+            }
+            // Ambient colors, texture maps and the like are not supported
+            _ => {}
+        }
+    }
+
+    Ok(entries
+        .into_iter()
+        .map(|(name, entry)| (name, entry.material()))
+        .collect())
+}
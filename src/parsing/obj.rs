@@ -0,0 +1,327 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::str::SplitWhitespace;
+use std::sync::Arc;
+
+use super::error::ObjError;
+use super::mtl;
+use crate::color::Color;
+use crate::material::{Lambertian, Material};
+use crate::mesh::{FaceGroup, TriangleMesh};
+use crate::vec2::UV;
+use crate::vec3::{self, Point3, Vec3};
+
+/// Albedo of the faces that use no material, and of materials without a `Kd`.
+pub(super) const DEFAULT_ALBEDO: f64 = 0.8;
+
+/// Reads the Wavefront OBJ file at `path` into a [`TriangleMesh`].
+///
+/// Supported statements are vertex positions (`v`), normals (`vn`), texture
+/// coordinates (`vt`), faces (`f`) and materials (`mtllib`, `usemtl`). Faces may
+/// have any number of vertices, they are split into a fan of triangles, and
+/// indices may be negative to count back from the last vertex. Groups and
+/// objects (`g`, `o`) are accepted but the file is loaded as a single mesh;
+/// other statements, like lines or free-form curves, are ignored.
+///
+/// Material libraries are looked up relative to the OBJ file, and their
+/// materials are mapped onto the ones of the renderer:
+///
+/// * emissive materials (`Ke`) become [`DiffuseLight`](crate::DiffuseLight)s,
+/// * transparent materials (`d` or `Tr`, or `illum` 4, 6, 7 or 9) become
+///   [`Dielectric`](crate::Dielectric)s with the index of refraction `Ni`,
+/// * reflective materials (`illum` 3, 5 or 8) become [`Metal`](crate::Metal)s
+///   colored by `Ks`, whose fuzz decreases as the exponent `Ns` grows,
+/// * everything else becomes a [`Lambertian`](crate::Lambertian) colored by `Kd`.
+///
+/// Faces that use no material are light gray.
+///
+/// # Errors
+///
+/// Returns an [`ObjError`] if a file cannot be read, or giving the file and line
+/// of the first malformed statement, such as a face referring to a vertex that
+/// does not exist or an unknown material.
+pub fn load_obj<P: AsRef<Path>>(path: P) -> Result<TriangleMesh, ObjError> {
+    let path = path.as_ref();
+    let src = read(path)?;
+    ObjParser::new(Some(path)).parse(&src)
+}
+
+/// Parses the content of a Wavefront OBJ file into a [`TriangleMesh`].
+///
+/// Works like [`load_obj`], except that there is no file to find material
+/// libraries from: `mtllib` and `usemtl` are ignored and every face is light gray.
+///
+/// # Example
+///
+/// ```
+/// use raytracer::parsing::parse_obj;
+///
+/// let mesh = parse_obj("
+///     v 0 0 0
+///     v 1 0 0
+///     v 1 1 0
+///     v 0 1 0
+///     f 1 2 3 4
+///     f -4 -2 -1
+/// ").unwrap();
+/// assert_eq!(mesh.vertex_count(), 4);
+/// assert_eq!(mesh.face_count(), 3);
+/// ```
+pub fn parse_obj(src: &str) -> Result<TriangleMesh, ObjError> {
+    ObjParser::new(None).parse(src)
+}
+
+/// Reads a whole file, remembering its path in the error.
+pub(super) fn read(path: &Path) -> Result<String, ObjError> {
+    fs::read_to_string(path).map_err(|error| ObjError::Io {
+        path: path.to_path_buf(),
+        error,
+    })
+}
+
+/// Reads the numbers following a statement, at least `required` and at most `N`.
+///
+/// The values that are not given are 0.
+pub(super) fn numbers<const N: usize>(
+    tokens: SplitWhitespace,
+    required: usize,
+) -> Result<[f64; N], String> {
+    let mut values = [0.0; N];
+    let mut count = 0;
+    for token in tokens {
+        if count == N {
+            return Err(format!("expected at most {} numbers", N));
+        }
+        values[count] = token
+            .parse()
+            .map_err(|_| format!("expected a number, found \"{}\"", token))?;
+        count += 1;
+    }
+    if count < required {
+        return Err(format!("expected at least {} numbers", required));
+    }
+    Ok(values)
+}
+
+/// Indices of the position, texture coordinates and normal of a face vertex.
+type VertexKey = (usize, Option<usize>, Option<usize>);
+
+/// Gathers the statements of an OBJ file into the buffers of a mesh.
+///
+/// OBJ faces index positions, texture coordinates and normals separately, while
+/// a mesh shares a single index between them: every distinct combination used by
+/// a face becomes a vertex of the mesh.
+struct ObjParser<'p> {
+    /// The file being parsed, `None` when parsing a string.
+    file: Option<&'p Path>,
+    positions: Vec<Point3>,
+    normals: Vec<Vec3>,
+    uvs: Vec<UV>,
+    /// Materials of the libraries loaded so far, by name.
+    library: HashMap<String, Arc<dyn Material>>,
+    /// Name of the material used by the next faces, `None` for the default one.
+    current_material: Option<String>,
+
+    vertices: HashMap<VertexKey, u32>,
+    mesh_positions: Vec<Point3>,
+    mesh_normals: Vec<Option<Vec3>>,
+    mesh_uvs: Vec<Option<UV>>,
+    groups: Vec<FaceGroup>,
+    /// Index in `groups` of the faces using each material.
+    group_of: HashMap<Option<String>, usize>,
+}
+
+impl<'p> ObjParser<'p> {
+    fn new(file: Option<&'p Path>) -> Self {
+        ObjParser {
+            file,
+            positions: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            library: HashMap::new(),
+            current_material: None,
+            vertices: HashMap::new(),
+            mesh_positions: Vec::new(),
+            mesh_normals: Vec::new(),
+            mesh_uvs: Vec::new(),
+            groups: Vec::new(),
+            group_of: HashMap::new(),
+        }
+    }
+
+    fn parse(mut self, src: &str) -> Result<TriangleMesh, ObjError> {
+        for (i, line) in src.lines().enumerate() {
+            let line_number = i + 1;
+            let line = line.split('#').next().unwrap_or_default();
+            let mut tokens = line.split_whitespace();
+            let Some(keyword) = tokens.next() else {
+                continue;
+            };
+
+            match keyword {
+                "v" => {
+                    let [x, y, z, _w] = numbers(tokens, 3).map_err(|m| self.error(line_number, m))?;
+                    self.positions.push(Point3::new(x, y, z));
+                }
+                "vn" => {
+                    let [x, y, z] = numbers(tokens, 3).map_err(|m| self.error(line_number, m))?;
+                    self.normals.push(Vec3::new(x, y, z));
+                }
+                "vt" => {
+                    let [u, v, _w] = numbers(tokens, 1).map_err(|m| self.error(line_number, m))?;
+                    self.uvs.push(UV::new(u, v));
+                }
+                "f" => self.face(tokens, line_number)?,
+                "mtllib" => {
+                    if let Some(dir) = self.file.map(|file| file.parent().unwrap_or(Path::new(""))) {
+                        for name in tokens {
+                            self.library.extend(mtl::load_mtl(&dir.join(name))?);
+                        }
+                    }
+                }
+                "usemtl" => {
+                    let name = line.trim_start()[keyword.len()..].trim();
+                    if self.file.is_some() {
+                        if !self.library.contains_key(name) {
+                            return Err(self.error(line_number, format!("unknown material \"{}\"", name)));
+                        }
+                        self.current_material = Some(name.to_string());
+                    }
+                }
+                // Lines, curves, smoothing groups and the like are not supported
+                _ => {}
+            }
+        }
+
+        Ok(self.into_mesh())
+    }
+
+    /// Adds a face, split into a fan of triangles around its first vertex.
+    fn face(&mut self, tokens: SplitWhitespace, line_number: usize) -> Result<(), ObjError> {
+        let corners = tokens
+            .map(|token| self.face_vertex(token).map_err(|m| self.error(line_number, m)))
+            .collect::<Result<Vec<_>, _>>()?;
+        if corners.len() < 3 {
+            return Err(self.error(line_number, "a face needs at least 3 vertices"));
+        }
+
+        let group = match self.group_of.get(&self.current_material) {
+            Some(&group) => group,
+            None => {
+                let material = match &self.current_material {
+                    Some(name) => self.library[name].clone(),
+                    None => default_material(),
+                };
+                self.groups.push(FaceGroup::new(Vec::new(), material));
+                self.group_of.insert(self.current_material.clone(), self.groups.len() - 1);
+                self.groups.len() - 1
+            }
+        };
+
+        let indices: Vec<u32> = corners.into_iter().map(|key| self.vertex(key)).collect();
+        for i in 1..indices.len() - 1 {
+            self.groups[group].faces.push([indices[0], indices[i], indices[i + 1]]);
+        }
+        Ok(())
+    }
+
+    /// Resolves a face vertex written `v`, `v/vt`, `v//vn` or `v/vt/vn`.
+    fn face_vertex(&self, token: &str) -> Result<VertexKey, String> {
+        let mut parts = token.split('/');
+        let position = parts.next().unwrap_or_default();
+        let uv = parts.next().filter(|s| !s.is_empty());
+        let normal = parts.next().filter(|s| !s.is_empty());
+        if parts.next().is_some() {
+            return Err(format!("malformed face vertex \"{}\"", token));
+        }
+
+        Ok((
+            resolve_index(position, self.positions.len(), "vertex")?,
+            uv.map(|s| resolve_index(s, self.uvs.len(), "texture coordinate")).transpose()?,
+            normal.map(|s| resolve_index(s, self.normals.len(), "normal")).transpose()?,
+        ))
+    }
+
+    /// Returns the index in the mesh of a face vertex, adding it the first time.
+    fn vertex(&mut self, key: VertexKey) -> u32 {
+        if let Some(&index) = self.vertices.get(&key) {
+            return index;
+        }
+
+        let (position, uv, normal) = key;
+        let index = self.mesh_positions.len() as u32;
+        self.mesh_positions.push(self.positions[position]);
+        self.mesh_uvs.push(uv.map(|i| self.uvs[i]));
+        // A null normal cannot be normalized, it is treated as missing
+        self.mesh_normals.push(normal.map(|i| self.normals[i]).filter(|n| !n.near_zero()));
+        self.vertices.insert(key, index);
+        index
+    }
+
+    /// Builds the mesh, filling in the attributes some vertices lack.
+    ///
+    /// Vertices without a normal, in a file that gives some, get the average of
+    /// the normals of the faces around them, weighted by area. Missing texture
+    /// coordinates are 0.
+    fn into_mesh(self) -> TriangleMesh {
+        let has_normals = self.mesh_normals.iter().any(Option::is_some);
+        let has_uvs = self.mesh_uvs.iter().any(Option::is_some);
+
+        let normals = has_normals.then(|| {
+            let mut sums = vec![Vec3::default(); self.mesh_positions.len()];
+            for face in self.groups.iter().flat_map(|group| &group.faces) {
+                let [p0, p1, p2] = face.map(|v| self.mesh_positions[v as usize]);
+                // The cross product is as long as twice the area of the face
+                let normal = vec3::cross(p1 - p0, p2 - p0);
+                for &v in face {
+                    sums[v as usize] += normal;
+                }
+            }
+            self.mesh_normals
+                .iter()
+                .zip(sums)
+                .map(|(normal, sum)| match normal {
+                    Some(normal) => *normal,
+                    None if sum.near_zero() => Vec3::new(0.0, 1.0, 0.0),
+                    None => sum,
+                })
+                .collect()
+        });
+        let uvs = has_uvs.then(|| self.mesh_uvs.iter().map(|uv| uv.unwrap_or_default()).collect());
+
+        let mut mesh = TriangleMesh::with_groups(self.mesh_positions, self.groups);
+        if let Some(normals) = normals {
+            mesh = mesh.with_normals(normals);
+        }
+        if let Some(uvs) = uvs {
+            mesh = mesh.with_uvs(uvs);
+        }
+        mesh
+    }
+
+    fn error(&self, line: usize, message: impl Into<String>) -> ObjError {
+        ObjError::Syntax {
+            file: self.file.map(Path::to_path_buf),
+            line,
+            message: message.into(),
+        }
+    }
+}
+
+/// Converts a 1-based index, or a negative one counting back from the last of
+/// the `count` elements declared so far, into a 0-based index.
+fn resolve_index(token: &str, count: usize, what: &str) -> Result<usize, String> {
+    let index: i64 = token
+        .parse()
+        .map_err(|_| format!("expected a {} index, found \"{}\"", what, token))?;
+    let resolved = if index < 0 { count as i64 + index } else { index - 1 };
+    if index == 0 || resolved < 0 || resolved >= count as i64 {
+        return Err(format!("{} {} does not exist, {} declared so far", what, index, count));
+    }
+    Ok(resolved as usize)
+}
+
+fn default_material() -> Arc<dyn Material> {
+    Arc::new(Lambertian::new(Color::new(DEFAULT_ALBEDO, DEFAULT_ALBEDO, DEFAULT_ALBEDO)))
+}
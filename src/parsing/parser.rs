@@ -0,0 +1,925 @@
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde_json::{Map, Value};
+
+use super::error::{Location, ParseError};
+use super::json_path::JsonPath;
+use super::obj::load_obj;
+use crate::bvh::Bvh;
+use crate::camera::Camera;
+use crate::cube::Cube;
+use crate::cylinder::Cylinder;
+use crate::disk::Disk;
+use crate::faceted_cylinder::FacetedCylinder;
+use crate::hittable::Hittable;
+use crate::hittable_list::HittableList;
+use crate::instance::Instance;
+use crate::material::{Dielectric, DiffuseLight, Lambertian, Material, Metal};
+use crate::quad::Quad;
+use crate::renderer::Background;
+use crate::scene::Scene;
+use crate::sphere::Sphere;
+use crate::texture::{
+    CheckerTexture, ColorRamp, Filter, ImageTexture, MarbleTexture, NoiseTexture, SolidColor, Texture,
+    WoodTexture, WrapMode,
+};
+use crate::transform::{Transform, Transformed};
+use crate::triangle::Triangle;
+use crate::vec2::UV;
+use crate::vec3::{self, Point3, Vec3};
+
+/// Number of quads used for a faceted cylinder when the scene does not specify it.
+const DEFAULT_CYLINDER_SEGMENTS: usize = 32;
+
+/// Beyond this, the finest layers of noise are smaller than the precision of a point.
+const MAX_OCTAVES: u64 = 32;
+
+const TRANSFORM_STEPS: &str = "a single \"translate\", \"scale\", \"rotate\", \"rotate_x\", \"rotate_y\" or \"rotate_z\" step";
+const MATERIAL_TYPES: &str = "\"lambertian\", \"metal\", \"dielectric\" or \"diffuse_light\"";
+const TEXTURE_TYPES: &str = "\"checker\", \"image\", \"noise\", \"marble\" or \"wood\"";
+const WRAP_MODES: &str = "\"repeat\", \"clamp\" or \"mirror\"";
+const FILTERS: &str = "\"nearest\" or \"bilinear\"";
+const OBJECT_TYPES: &str =
+    "\"sphere\", \"quad\", \"triangle\", \"cube\", \"cylinder\", \"faceted_cylinder\", \"disk\", \"mesh\" or \"instance\"";
+
+/// Reads and parses the JSON scene file at `path`.
+///
+/// See [`parse_scene`] for the expected format. The paths of the meshes are
+/// relative to the directory of the scene file.
+pub fn load_scene<P: AsRef<Path>>(path: P) -> Result<Scene, ParseError> {
+    let path = path.as_ref();
+    let src = fs::read_to_string(path)?;
+    let root: Value = serde_json::from_str(&src)?;
+    SceneParser {
+        src: &src,
+        dir: path.parent().unwrap_or(Path::new("")),
+    }
+    .scene(&root)
+}
+
+/// Parses a JSON scene description into a [`Scene`].
+///
+/// The root object holds the following keys:
+///
+/// * `camera` - the arguments of [`Camera::new`]. `lookfrom`, `lookat` and `vfov`
+///   are required; `vup` defaults to `[0, 1, 0]`, `aspect_ratio` to `1.5`,
+///   `aperture` to `0` and `focus_dist` to the distance between `lookfrom` and `lookat`.
+/// * `background` - optional, what rays leaving the scene see: `"sky"`, the default,
+///   or a color. A black background leaves the scene lit by its lights only.
+/// * `materials` - an object mapping names to materials. The `type` of a material is
+///   one of `lambertian` (`albedo`), `metal` (`albedo`, `fuzz`), `dielectric` (`ir`)
+///   or `diffuse_light` (`emit`, a color that may be brighter than 1).
+///   An `albedo` is either a color or a texture: `{ "type": "checker", "scale": s,
+///   "even": ..., "odd": ... }` alternates between two textures in cubes of side `s`,
+///   `{ "type": "image", "path": ... }` maps a PPM, PNG or JPEG file with an optional
+///   `wrap` (`"repeat"`, `"clamp"` or `"mirror"`) and `filter` (`"nearest"` or
+///   `"bilinear"`). `noise`, `marble` and `wood` are procedural textures of features
+///   about `1 / scale` units wide, with optional `octaves` of detail, a `seed`, a
+///   `distortion` of the veins or rings, and `colors` spread evenly along the pattern.
+/// * `objects` - an array of objects, each with a `type` and a `material` name:
+///   `sphere` (`center`, `radius`), `quad` (`q`, `u`, `v`), `triangle` (`v0`, `v1`,
+///   `v2`, optional per-vertex `normals` and `uvs`), `cube` (`min` and `max`, or
+///   `center` and `size`), `cylinder` (`radius`, optional `capped` defaulting to
+///   `true`), `faceted_cylinder` (`radius`, optional `segments`) or `disk`
+///   (`center`, `radius`, optional `normal`). The ends of a cylinder are either
+///   centered on `p0` and `p1`, or given by a `center`, a `height` and an
+///   optional `axis`. Disks and cylinders face up along Y by default.
+///
+///   A `mesh` loads the Wavefront OBJ file at `path` with [`load_obj`]; its
+///   `material` is optional and replaces the materials of the OBJ file.
+///   An `instance` places the `prototype` of that name; its `material` is
+///   optional too and replaces the materials of the prototype.
+///
+///   Any object can be moved with an optional `transform`, a list of steps applied
+///   in order: `{ "translate": [x, y, z] }`, `{ "scale": s }` or `{ "scale": [x, y, z] }`,
+///   `{ "rotate_x": degrees }` (and `rotate_y`, `rotate_z`) or
+///   `{ "rotate": { "axis": [x, y, z], "angle": degrees } }`.
+///
+///   Spheres, quads and disks with a `diffuse_light` material, and no transform,
+///   are also the scene's [`lights`](Scene::lights): the renderer sends rays
+///   towards them directly.
+/// * `prototypes` - an object mapping names to arrays of objects, written like
+///   `objects`. A prototype is built once and shared by all its instances.
+///
+/// Vectors and colors are written as arrays of three numbers.
+///
+/// # Errors
+///
+/// Returns a [`ParseError`] naming the JSON path, line and column of the first
+/// problem found, such as a missing field, an unknown material or a negative radius.
+///
+/// # Example
+///
+/// ```
+/// use raytracer::parsing::parse_scene;
+///
+/// let scene = parse_scene(r#"{
+///     "camera": { "lookfrom": [2, 1, 2], "lookat": [0, 0, 0], "vfov": 40 },
+///     "materials": { "red": { "type": "lambertian", "albedo": [0.9, 0.2, 0.3] } },
+///     "objects": [
+///         { "type": "sphere", "center": [0, 0.5, 0], "radius": 0.5, "material": "red" }
+///     ]
+/// }"#).unwrap();
+/// ```
+pub fn parse_scene(src: &str) -> Result<Scene, ParseError> {
+    let root: Value = serde_json::from_str(src)?;
+    SceneParser {
+        src,
+        dir: Path::new(""),
+    }
+    .scene(&root)
+}
+
+/// Walks a parsed JSON document, keeping the source around to locate errors.
+struct SceneParser<'s> {
+    src: &'s str,
+    /// Directory the paths of the scene are relative to.
+    dir: &'s Path,
+}
+
+impl SceneParser<'_> {
+    fn scene(&self, root: &Value) -> Result<Scene, ParseError> {
+        let path = JsonPath::root();
+        let obj = self.object(root, &path)?;
+
+        let camera = self.camera(self.field(obj, "camera", &path)?, &path.key("camera"))?;
+
+        let materials = match obj.get("materials") {
+            Some(value) => self.materials(value, &path.key("materials"))?,
+            None => HashMap::new(),
+        };
+
+        let mut prototypes = HashMap::new();
+        if let Some(value) = obj.get("prototypes") {
+            let prototypes_path = path.key("prototypes");
+            for (name, value) in self.object(value, &prototypes_path)? {
+                // Prototypes cannot instantiate each other
+                let list = self.object_list(value, &prototypes_path.key(name), &materials, &HashMap::new(), None)?;
+                let prototype: Arc<dyn Hittable> = Arc::new(Bvh::new(list));
+                prototypes.insert(name.clone(), prototype);
+            }
+        }
+
+        let mut lights = HittableList::new();
+        let world = match obj.get("objects") {
+            Some(value) => self.object_list(value, &path.key("objects"), &materials, &prototypes, Some(&mut lights))?,
+            None => HittableList::new(),
+        };
+
+        let background = match obj.get("background") {
+            Some(value) => self.background(value, &path.key("background"))?,
+            None => Background::Sky,
+        };
+
+        Ok(Scene::new(world, camera)
+            .with_background(background)
+            .with_lights(lights))
+    }
+
+    /// Reads a background, either `"sky"` or a color.
+    fn background(&self, value: &Value, path: &JsonPath) -> Result<Background, ParseError> {
+        if let Some(name) = value.as_str() {
+            return match name {
+                "sky" => Ok(Background::Sky),
+                other => Err(self.unknown_type(path, other, "\"sky\" or a color")),
+            };
+        }
+        if !value.is_array() {
+            return Err(self.wrong_type(path, "\"sky\" or a color"));
+        }
+        Ok(Background::Solid(self.color(value, path)?))
+    }
+
+    /// Reads an array of objects. The emissive ones that can be sampled as lights
+    /// are also added to `lights`, if given.
+    fn object_list(
+        &self,
+        value: &Value,
+        path: &JsonPath,
+        materials: &HashMap<String, Arc<dyn Material>>,
+        prototypes: &HashMap<String, Arc<dyn Hittable>>,
+        mut lights: Option<&mut HittableList>,
+    ) -> Result<HittableList, ParseError> {
+        let objects = value
+            .as_array()
+            .ok_or_else(|| self.wrong_type(path, "an array of objects"))?;
+        let mut list = HittableList::new();
+        for (i, object) in objects.iter().enumerate() {
+            list.add(self.object_entry(object, &path.index(i), materials, prototypes, lights.as_deref_mut())?);
+        }
+        Ok(list)
+    }
+
+    fn camera(&self, value: &Value, path: &JsonPath) -> Result<Camera, ParseError> {
+        let obj = self.object(value, path)?;
+
+        let lookfrom = self.vec3_field(obj, "lookfrom", path)?;
+        let lookat = self.vec3_field(obj, "lookat", path)?;
+        if (lookfrom - lookat).near_zero() {
+            return Err(self.invalid(&path.key("lookat"), "a point different from lookfrom"));
+        }
+
+        let vfov = self.number_field(obj, "vfov", path)?;
+        if vfov <= 0.0 || vfov >= 180.0 {
+            return Err(self.invalid(&path.key("vfov"), "an angle between 0 and 180 degrees"));
+        }
+
+        let vup = match obj.get("vup") {
+            Some(value) => self.vec3(value, &path.key("vup"))?,
+            None => Vec3::new(0.0, 1.0, 0.0),
+        };
+        if vup.near_zero() {
+            return Err(self.invalid(&path.key("vup"), "a non-zero vector"));
+        }
+        if vec3::cross(vup, lookfrom - lookat).near_zero() {
+            return Err(self.invalid(
+                &path.key("vup"),
+                "a vector not parallel to the viewing direction",
+            ));
+        }
+
+        let aspect_ratio = self.optional_number(obj, "aspect_ratio", path)?.unwrap_or(3.0 / 2.0);
+        if aspect_ratio <= 0.0 {
+            return Err(self.invalid(&path.key("aspect_ratio"), "a positive number"));
+        }
+        let aperture = self.optional_number(obj, "aperture", path)?.unwrap_or(0.0);
+        if aperture < 0.0 {
+            return Err(self.invalid(&path.key("aperture"), "a non-negative number"));
+        }
+        let focus_dist = self
+            .optional_number(obj, "focus_dist", path)?
+            .unwrap_or_else(|| (lookfrom - lookat).length());
+        if focus_dist <= 0.0 {
+            return Err(self.invalid(&path.key("focus_dist"), "a positive distance"));
+        }
+
+        Ok(Camera::new(
+            lookfrom,
+            lookat,
+            vup,
+            vfov,
+            aspect_ratio,
+            aperture,
+            focus_dist,
+        ))
+    }
+
+    fn materials(
+        &self,
+        value: &Value,
+        path: &JsonPath,
+    ) -> Result<HashMap<String, Arc<dyn Material>>, ParseError> {
+        let obj = self.object(value, path)?;
+        let mut materials = HashMap::new();
+        for (name, value) in obj {
+            let material = self.material(value, &path.key(name))?;
+            materials.insert(name.clone(), material);
+        }
+        Ok(materials)
+    }
+
+    fn material(&self, value: &Value, path: &JsonPath) -> Result<Arc<dyn Material>, ParseError> {
+        let obj = self.object(value, path)?;
+        let kind = self.string_field(obj, "type", path)?;
+
+        let material: Arc<dyn Material> = match kind {
+            "lambertian" => {
+                let albedo = self.texture_field(obj, "albedo", path)?;
+                Arc::new(Lambertian::from_texture(albedo))
+            }
+            "metal" => {
+                let albedo = self.texture_field(obj, "albedo", path)?;
+                let fuzz = self.optional_number(obj, "fuzz", path)?.unwrap_or(0.0);
+                if fuzz < 0.0 {
+                    return Err(self.invalid(&path.key("fuzz"), "a non-negative number"));
+                }
+                Arc::new(Metal::from_texture(albedo, fuzz))
+            }
+            "dielectric" => {
+                let ir = self.number_field(obj, "ir", path)?;
+                if ir <= 0.0 {
+                    return Err(self.invalid(&path.key("ir"), "a positive index of refraction"));
+                }
+                Arc::new(Dielectric::new(ir))
+            }
+            "diffuse_light" => {
+                let emit = self.color_field(obj, "emit", path)?;
+                Arc::new(DiffuseLight::new(emit))
+            }
+            other => return Err(self.unknown_type(&path.key("type"), other, MATERIAL_TYPES)),
+        };
+        Ok(material)
+    }
+
+    fn object_entry(
+        &self,
+        value: &Value,
+        path: &JsonPath,
+        materials: &HashMap<String, Arc<dyn Material>>,
+        prototypes: &HashMap<String, Arc<dyn Hittable>>,
+        lights: Option<&mut HittableList>,
+    ) -> Result<Box<dyn Hittable>, ParseError> {
+        let obj = self.object(value, path)?;
+        let transform = obj
+            .get("transform")
+            .map(|value| self.transform(value, &path.key("transform")))
+            .transpose()?;
+
+        if obj.get("type").and_then(Value::as_str) == Some("instance") {
+            let name = self.string_field(obj, "prototype", path)?;
+            let prototype = prototypes.get(name).cloned().ok_or_else(|| {
+                ParseError::UnknownPrototype {
+                    location: self.location(&path.key("prototype")),
+                    name: name.to_string(),
+                }
+            })?;
+            let mut instance = Instance::new(prototype, transform.unwrap_or_default());
+            if let Some(value) = obj.get("material") {
+                instance = instance.with_material(self.material_ref(value, &path.key("material"), materials)?);
+            }
+            return Ok(Box::new(instance));
+        }
+
+        match transform {
+            Some(transform) => Ok(Box::new(Transformed::new(self.shape(obj, path, materials, None)?, transform))),
+            None => self.shape(obj, path, materials, lights),
+        }
+    }
+
+    /// Reads an object in its own space, before it is transformed.
+    ///
+    /// Emissive spheres, quads and disks are also added to `lights`, if given.
+    fn shape(
+        &self,
+        obj: &Map<String, Value>,
+        path: &JsonPath,
+        materials: &HashMap<String, Arc<dyn Material>>,
+        lights: Option<&mut HittableList>,
+    ) -> Result<Box<dyn Hittable>, ParseError> {
+        let kind = self.string_field(obj, "type", path)?;
+        if kind == "mesh" {
+            return self.mesh(obj, path, materials);
+        }
+
+        let mat = self.material_ref(self.field(obj, "material", path)?, &path.key("material"), materials)?;
+        let lights = lights.filter(|_| mat.is_emissive());
+
+        let object: Box<dyn Hittable> = match kind {
+            "sphere" => {
+                let center = self.vec3_field(obj, "center", path)?;
+                let radius = self.positive_field(obj, "radius", path)?;
+                sampled_light(Sphere::new(center, radius, mat), lights)
+            }
+            "quad" => {
+                let q = self.vec3_field(obj, "q", path)?;
+                let u = self.vec3_field(obj, "u", path)?;
+                let v = self.vec3_field(obj, "v", path)?;
+                if vec3::cross(u, v).near_zero() {
+                    return Err(self.invalid(&path.key("v"), "a vector not parallel to u"));
+                }
+                sampled_light(Quad::new(q, u, v, mat), lights)
+            }
+            "triangle" => {
+                let v0 = self.vec3_field(obj, "v0", path)?;
+                let v1 = self.vec3_field(obj, "v1", path)?;
+                let v2 = self.vec3_field(obj, "v2", path)?;
+                if vec3::cross(v1 - v0, v2 - v0).near_zero() {
+                    return Err(self.invalid(&path.key("v2"), "a vertex not aligned with v0 and v1"));
+                }
+                let mut triangle = Triangle::new(v0, v1, v2, mat);
+                if let Some(value) = obj.get("normals") {
+                    let normals_path = path.key("normals");
+                    let normals = self.triple(value, &normals_path, |value, path| {
+                        let normal = self.vec3(value, path)?;
+                        if normal.near_zero() {
+                            return Err(self.invalid(path, "a non-zero vector"));
+                        }
+                        Ok(normal)
+                    })?;
+                    triangle = triangle.with_normals(normals);
+                }
+                if let Some(value) = obj.get("uvs") {
+                    let uvs = self.triple(value, &path.key("uvs"), |value, path| self.uv(value, path))?;
+                    triangle = triangle.with_uvs(uvs);
+                }
+                Box::new(triangle)
+            }
+            "cube" => {
+                if obj.contains_key("center") {
+                    let center = self.vec3_field(obj, "center", path)?;
+                    let size = self.positive_field(obj, "size", path)?;
+                    Box::new(Cube::from_center(center, size, mat))
+                } else {
+                    let min = self.vec3_field(obj, "min", path)?;
+                    let max = self.vec3_field(obj, "max", path)?;
+                    if min.x() >= max.x() || min.y() >= max.y() || min.z() >= max.z() {
+                        return Err(self.invalid(
+                            &path.key("max"),
+                            "a corner greater than min on every axis",
+                        ));
+                    }
+                    Box::new(Cube::new(min, max, mat))
+                }
+            }
+            "cylinder" => {
+                let (p0, p1) = self.cylinder_ends(obj, path)?;
+                let radius = self.positive_field(obj, "radius", path)?;
+                let capped = match obj.get("capped") {
+                    Some(value) => value
+                        .as_bool()
+                        .ok_or_else(|| self.wrong_type(&path.key("capped"), "a boolean"))?,
+                    None => true,
+                };
+                Box::new(Cylinder::between(p0, p1, radius, mat).capped(capped))
+            }
+            "faceted_cylinder" => {
+                let (p0, p1) = self.cylinder_ends(obj, path)?;
+                let radius = self.positive_field(obj, "radius", path)?;
+                let segments = match obj.get("segments") {
+                    Some(value) => {
+                        let segments_path = path.key("segments");
+                        let segments = value.as_u64().ok_or_else(|| {
+                            self.wrong_type(&segments_path, "a non-negative integer")
+                        })?;
+                        if segments < 3 {
+                            return Err(self.invalid(&segments_path, "at least 3 segments"));
+                        }
+                        segments as usize
+                    }
+                    None => DEFAULT_CYLINDER_SEGMENTS,
+                };
+                Box::new(FacetedCylinder::between(p0, p1, radius, mat, segments))
+            }
+            "disk" => {
+                let center = self.vec3_field(obj, "center", path)?;
+                let radius = self.positive_field(obj, "radius", path)?;
+                let normal = self.direction_field(obj, "normal", path)?;
+                sampled_light(Disk::with_normal(center, normal, radius, mat), lights)
+            }
+            other => return Err(self.unknown_type(&path.key("type"), other, OBJECT_TYPES)),
+        };
+        Ok(object)
+    }
+
+    /// Reads a list of steps, each an object with a single key, composed in order.
+    fn transform(&self, value: &Value, path: &JsonPath) -> Result<Transform, ParseError> {
+        let steps = value
+            .as_array()
+            .ok_or_else(|| self.wrong_type(path, "an array of transform steps"))?;
+
+        let mut transform = Transform::identity();
+        for (i, step) in steps.iter().enumerate() {
+            let step_path = path.index(i);
+            let obj = self.object(step, &step_path)?;
+            let (kind, value) = match obj.iter().next() {
+                Some(entry) if obj.len() == 1 => entry,
+                _ => return Err(self.invalid(&step_path, TRANSFORM_STEPS)),
+            };
+            let value_path = step_path.key(kind);
+
+            let step = match kind.as_str() {
+                "translate" => Transform::translate(self.vec3(value, &value_path)?),
+                "scale" => {
+                    let factors = match value.as_f64() {
+                        Some(factor) => Vec3::new(factor, factor, factor),
+                        None => self.vec3(value, &value_path).map_err(|_| {
+                            self.wrong_type(&value_path, "a number or an array of three numbers")
+                        })?,
+                    };
+                    if factors.x() == 0.0 || factors.y() == 0.0 || factors.z() == 0.0 {
+                        return Err(self.invalid(&value_path, "non-zero scale factors"));
+                    }
+                    Transform::scale(factors)
+                }
+                "rotate_x" => Transform::rotate_x(self.number(value, &value_path)?),
+                "rotate_y" => Transform::rotate_y(self.number(value, &value_path)?),
+                "rotate_z" => Transform::rotate_z(self.number(value, &value_path)?),
+                "rotate" => {
+                    let rotation = self.object(value, &value_path)?;
+                    let axis = self.vec3_field(rotation, "axis", &value_path)?;
+                    if axis.near_zero() {
+                        return Err(self.invalid(&value_path.key("axis"), "a non-zero vector"));
+                    }
+                    Transform::rotate(axis, self.number_field(rotation, "angle", &value_path)?)
+                }
+                _ => return Err(self.invalid(&step_path, TRANSFORM_STEPS)),
+            };
+            transform = transform.then(&step);
+        }
+        Ok(transform)
+    }
+
+    /// Reads the centers of the ends of a cylinder, given either as `p0` and `p1`,
+    /// or as a `center`, a `height` and an optional `axis`.
+    fn cylinder_ends(
+        &self,
+        obj: &Map<String, Value>,
+        path: &JsonPath,
+    ) -> Result<(Point3, Point3), ParseError> {
+        if obj.contains_key("p0") {
+            let p0 = self.vec3_field(obj, "p0", path)?;
+            let p1 = self.vec3_field(obj, "p1", path)?;
+            if (p1 - p0).near_zero() {
+                return Err(self.invalid(&path.key("p1"), "a point different from p0"));
+            }
+            Ok((p0, p1))
+        } else {
+            let center = self.vec3_field(obj, "center", path)?;
+            let height = self.positive_field(obj, "height", path)?;
+            let axis = self.direction_field(obj, "axis", path)?;
+            let half_height = 0.5 * height * vec3::unit_vector(axis);
+            Ok((center - half_height, center + half_height))
+        }
+    }
+
+    fn mesh(
+        &self,
+        obj: &Map<String, Value>,
+        path: &JsonPath,
+        materials: &HashMap<String, Arc<dyn Material>>,
+    ) -> Result<Box<dyn Hittable>, ParseError> {
+        let file = self.dir.join(self.string_field(obj, "path", path)?);
+        let mut mesh = load_obj(file).map_err(|error| ParseError::Mesh {
+            location: self.location(&path.key("path")),
+            error,
+        })?;
+        if let Some(value) = obj.get("material") {
+            mesh = mesh.with_material(self.material_ref(value, &path.key("material"), materials)?);
+        }
+        Ok(Box::new(mesh))
+    }
+
+    /// Looks up the material named by `value` among the declared ones.
+    fn material_ref(
+        &self,
+        value: &Value,
+        path: &JsonPath,
+        materials: &HashMap<String, Arc<dyn Material>>,
+    ) -> Result<Arc<dyn Material>, ParseError> {
+        let name = value
+            .as_str()
+            .ok_or_else(|| self.wrong_type(path, "a string"))?;
+        materials.get(name).cloned().ok_or_else(|| ParseError::UnknownMaterial {
+            location: self.location(path),
+            name: name.to_string(),
+        })
+    }
+
+    // Errors
+
+    fn location(&self, path: &JsonPath) -> Location {
+        let (line, column) = path.locate(self.src);
+        Location {
+            path: path.to_string(),
+            line,
+            column,
+        }
+    }
+
+    fn wrong_type(&self, path: &JsonPath, expected: &'static str) -> ParseError {
+        ParseError::WrongType {
+            location: self.location(path),
+            expected,
+        }
+    }
+
+    fn invalid(&self, path: &JsonPath, expected: &'static str) -> ParseError {
+        ParseError::InvalidValue {
+            location: self.location(path),
+            expected,
+        }
+    }
+
+    fn unknown_type(&self, path: &JsonPath, found: &str, expected: &'static str) -> ParseError {
+        ParseError::UnknownType {
+            location: self.location(path),
+            found: found.to_string(),
+            expected,
+        }
+    }
+
+    // Typed accessors
+
+    fn object<'v>(
+        &self,
+        value: &'v Value,
+        path: &JsonPath,
+    ) -> Result<&'v Map<String, Value>, ParseError> {
+        value
+            .as_object()
+            .ok_or_else(|| self.wrong_type(path, "an object"))
+    }
+
+    fn field<'v>(
+        &self,
+        obj: &'v Map<String, Value>,
+        key: &str,
+        path: &JsonPath,
+    ) -> Result<&'v Value, ParseError> {
+        obj.get(key).ok_or_else(|| {
+            // The field does not exist in the source, point at the object that lacks it
+            let (line, column) = path.locate(self.src);
+            ParseError::MissingField {
+                location: Location {
+                    path: path.key(key).to_string(),
+                    line,
+                    column,
+                },
+            }
+        })
+    }
+
+    fn number(&self, value: &Value, path: &JsonPath) -> Result<f64, ParseError> {
+        value
+            .as_f64()
+            .ok_or_else(|| self.wrong_type(path, "a number"))
+    }
+
+    fn number_field(
+        &self,
+        obj: &Map<String, Value>,
+        key: &str,
+        path: &JsonPath,
+    ) -> Result<f64, ParseError> {
+        self.number(self.field(obj, key, path)?, &path.key(key))
+    }
+
+    fn optional_number(
+        &self,
+        obj: &Map<String, Value>,
+        key: &str,
+        path: &JsonPath,
+    ) -> Result<Option<f64>, ParseError> {
+        obj.get(key)
+            .map(|value| self.number(value, &path.key(key)))
+            .transpose()
+    }
+
+    fn optional_integer(
+        &self,
+        obj: &Map<String, Value>,
+        key: &str,
+        path: &JsonPath,
+    ) -> Result<Option<u64>, ParseError> {
+        obj.get(key)
+            .map(|value| {
+                value
+                    .as_u64()
+                    .ok_or_else(|| self.wrong_type(&path.key(key), "a non-negative integer"))
+            })
+            .transpose()
+    }
+
+    /// Reads a required number that must be strictly positive, like a radius or a size.
+    fn positive_field(
+        &self,
+        obj: &Map<String, Value>,
+        key: &str,
+        path: &JsonPath,
+    ) -> Result<f64, ParseError> {
+        let value = self.number_field(obj, key, path)?;
+        if value <= 0.0 {
+            return Err(self.invalid(&path.key(key), "a positive number"));
+        }
+        Ok(value)
+    }
+
+    fn string_field<'v>(
+        &self,
+        obj: &'v Map<String, Value>,
+        key: &str,
+        path: &JsonPath,
+    ) -> Result<&'v str, ParseError> {
+        self.field(obj, key, path)?
+            .as_str()
+            .ok_or_else(|| self.wrong_type(&path.key(key), "a string"))
+    }
+
+    fn vec3(&self, value: &Value, path: &JsonPath) -> Result<Point3, ParseError> {
+        match value.as_array().map(|a| a.as_slice()) {
+            Some([x, y, z]) => Ok(Vec3::new(
+                self.number(x, &path.index(0))?,
+                self.number(y, &path.index(1))?,
+                self.number(z, &path.index(2))?,
+            )),
+            _ => Err(self.wrong_type(path, "an array of three numbers")),
+        }
+    }
+
+    fn uv(&self, value: &Value, path: &JsonPath) -> Result<UV, ParseError> {
+        match value.as_array().map(|a| a.as_slice()) {
+            Some([u, v]) => Ok(UV::new(
+                self.number(u, &path.index(0))?,
+                self.number(v, &path.index(1))?,
+            )),
+            _ => Err(self.wrong_type(path, "an array of two numbers")),
+        }
+    }
+
+    /// Reads an array of exactly three values, one per vertex of a triangle.
+    fn triple<T, F>(&self, value: &Value, path: &JsonPath, read: F) -> Result<[T; 3], ParseError>
+    where
+        F: Fn(&Value, &JsonPath) -> Result<T, ParseError>,
+    {
+        match value.as_array().map(|a| a.as_slice()) {
+            Some([a, b, c]) => Ok([
+                read(a, &path.index(0))?,
+                read(b, &path.index(1))?,
+                read(c, &path.index(2))?,
+            ]),
+            _ => Err(self.wrong_type(path, "an array of three values, one per vertex")),
+        }
+    }
+
+    fn vec3_field(
+        &self,
+        obj: &Map<String, Value>,
+        key: &str,
+        path: &JsonPath,
+    ) -> Result<Point3, ParseError> {
+        self.vec3(self.field(obj, key, path)?, &path.key(key))
+    }
+
+    /// Reads an optional non-zero vector giving an orientation, up along Y by default.
+    fn direction_field(
+        &self,
+        obj: &Map<String, Value>,
+        key: &str,
+        path: &JsonPath,
+    ) -> Result<Vec3, ParseError> {
+        let Some(value) = obj.get(key) else {
+            return Ok(Vec3::new(0.0, 1.0, 0.0));
+        };
+        let direction = self.vec3(value, &path.key(key))?;
+        if direction.near_zero() {
+            return Err(self.invalid(&path.key(key), "a non-zero vector"));
+        }
+        Ok(direction)
+    }
+
+    /// Reads a color, an array of three non-negative numbers.
+    fn color_field(
+        &self,
+        obj: &Map<String, Value>,
+        key: &str,
+        path: &JsonPath,
+    ) -> Result<Vec3, ParseError> {
+        self.color(self.field(obj, key, path)?, &path.key(key))
+    }
+
+    fn color(&self, value: &Value, path: &JsonPath) -> Result<Vec3, ParseError> {
+        let color = self.vec3(value, path)?;
+        if color.x() < 0.0 || color.y() < 0.0 || color.z() < 0.0 {
+            return Err(self.invalid(path, "a color with non-negative components"));
+        }
+        Ok(color)
+    }
+
+    /// Reads a texture, either a plain color or an object with a `type`.
+    fn texture_field(
+        &self,
+        obj: &Map<String, Value>,
+        key: &str,
+        path: &JsonPath,
+    ) -> Result<Arc<dyn Texture>, ParseError> {
+        self.texture(self.field(obj, key, path)?, &path.key(key))
+    }
+
+    fn texture(&self, value: &Value, path: &JsonPath) -> Result<Arc<dyn Texture>, ParseError> {
+        if value.is_array() {
+            return Ok(Arc::new(SolidColor::new(self.color(value, path)?)));
+        }
+        let obj = value
+            .as_object()
+            .ok_or_else(|| self.wrong_type(path, "a color or a texture object"))?;
+        let kind = self.string_field(obj, "type", path)?;
+
+        let texture: Arc<dyn Texture> = match kind {
+            "checker" => {
+                let scale = self.positive_field(obj, "scale", path)?;
+                let even = self.texture_field(obj, "even", path)?;
+                let odd = self.texture_field(obj, "odd", path)?;
+                Arc::new(CheckerTexture::new(scale, Box::new(even), Box::new(odd)))
+            }
+            "image" => {
+                let file = self.dir.join(self.string_field(obj, "path", path)?);
+                let mut image = ImageTexture::load(file).map_err(|error| ParseError::Image {
+                    location: self.location(&path.key("path")),
+                    error,
+                })?;
+                if obj.contains_key("wrap") {
+                    image = image.wrap(match self.string_field(obj, "wrap", path)? {
+                        "repeat" => WrapMode::Repeat,
+                        "clamp" => WrapMode::Clamp,
+                        "mirror" => WrapMode::Mirror,
+                        _ => return Err(self.invalid(&path.key("wrap"), WRAP_MODES)),
+                    });
+                }
+                if obj.contains_key("filter") {
+                    image = image.filter(match self.string_field(obj, "filter", path)? {
+                        "nearest" => Filter::Nearest,
+                        "bilinear" => Filter::Bilinear,
+                        _ => return Err(self.invalid(&path.key("filter"), FILTERS)),
+                    });
+                }
+                Arc::new(image)
+            }
+            "noise" | "marble" | "wood" => self.procedural(kind, obj, path)?,
+            other => return Err(self.unknown_type(&path.key("type"), other, TEXTURE_TYPES)),
+        };
+        Ok(texture)
+    }
+
+    /// Reads a `noise`, `marble` or `wood` texture, which share their settings.
+    fn procedural(
+        &self,
+        kind: &str,
+        obj: &Map<String, Value>,
+        path: &JsonPath,
+    ) -> Result<Arc<dyn Texture>, ParseError> {
+        let scale = self.positive_field(obj, "scale", path)?;
+        let octaves = match self.optional_integer(obj, "octaves", path)? {
+            Some(octaves) if !(1..=MAX_OCTAVES).contains(&octaves) => {
+                return Err(self.invalid(&path.key("octaves"), "from 1 to 32 octaves"));
+            }
+            octaves => octaves.map(|octaves| octaves as u32),
+        };
+        let seed = self.optional_integer(obj, "seed", path)?.unwrap_or(0);
+        let distortion = self.optional_number(obj, "distortion", path)?;
+        let ramp = obj
+            .get("colors")
+            .map(|value| self.color_ramp(value, &path.key("colors")))
+            .transpose()?;
+
+        let texture: Arc<dyn Texture> = match kind {
+            "noise" => {
+                let mut noise = NoiseTexture::new(scale).seed(seed);
+                if let Some(octaves) = octaves {
+                    noise = noise.octaves(octaves);
+                }
+                if let Some(ramp) = ramp {
+                    noise = noise.ramp(ramp);
+                }
+                Arc::new(noise)
+            }
+            "marble" => {
+                let mut marble = MarbleTexture::new(scale).seed(seed);
+                if let Some(octaves) = octaves {
+                    marble = marble.octaves(octaves);
+                }
+                if let Some(distortion) = distortion {
+                    marble = marble.distortion(distortion);
+                }
+                if let Some(ramp) = ramp {
+                    marble = marble.ramp(ramp);
+                }
+                Arc::new(marble)
+            }
+            _ => {
+                let mut wood = WoodTexture::new(scale).seed(seed);
+                if let Some(octaves) = octaves {
+                    wood = wood.octaves(octaves);
+                }
+                if let Some(distortion) = distortion {
+                    wood = wood.distortion(distortion);
+                }
+                if let Some(ramp) = ramp {
+                    wood = wood.ramp(ramp);
+                }
+                Arc::new(wood)
+            }
+        };
+        Ok(texture)
+    }
+
+    /// Reads a non-empty array of colors, evenly spaced along a ramp.
+    fn color_ramp(&self, value: &Value, path: &JsonPath) -> Result<ColorRamp, ParseError> {
+        let values = value
+            .as_array()
+            .ok_or_else(|| self.wrong_type(path, "an array of colors"))?;
+        if values.is_empty() {
+            return Err(self.invalid(path, "at least one color"));
+        }
+        let colors = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| self.color(value, &path.index(i)))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(ColorRamp::from_colors(&colors))
+    }
+}
+
+/// Boxes `shape` for the world, also adding it to `lights` if given, both sharing it.
+fn sampled_light<S: Hittable + 'static>(shape: S, lights: Option<&mut HittableList>) -> Box<dyn Hittable> {
+    match lights {
+        Some(lights) => {
+            let shape = Arc::new(shape);
+            lights.add(Box::new(shape.clone()));
+            Box::new(shape)
+        }
+        None => Box::new(shape),
+    }
+}
@@ -2,12 +2,18 @@ use crate::vec3::*;
 use crate::material::*;
 use std::sync::Arc;
 use crate::hittable::{HitRecord,Hittable};
+use crate::aabb::Aabb;
+use crate::vec2::UV;
+use crate::common;
+use crate::ray::Ray;
 
 /// Represents a quadrilateral (quad) in 3D space defined by a point `q`
 /// and two edge vectors `u` and `v`.
 ///
 /// The quad lies on a plane, with `q` as one corner, `u` and `v` defining its sides,
 /// and a shared material reference via `Arc<dyn Material>`.
+///
+/// Its texture coordinates run from 0 to 1 along `u` and along `v`.
 pub struct Quad {
     /// Origin point of the quad (one of its corners).
     q: Point3,
@@ -21,6 +27,8 @@ pub struct Quad {
     normal: Vec3,
     /// Plane distance from origin used in the plane equation.
     d: f64,
+    /// Area of the quad, used when sampling it as a light.
+    area: f64,
     /// Shared reference to the material applied to the quad.
     mat: Arc<dyn Material>,
 }
@@ -51,6 +59,7 @@ impl Quad {
             w,
             mat,
             d,
+            area: n.length(),
             normal,
         }
     }
@@ -70,7 +79,7 @@ impl Hittable for Quad {
     /// # Returns
     /// * `Some(HitRecord)` if the ray hits the quad.
     /// * `None` if there's no valid intersection.
-    fn hit(&self, ray: &crate::ray::Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
         let denom = dot(self.normal, ray.direction());
 
         // If the ray is parallel to the quad's plane
@@ -91,7 +100,7 @@ impl Hittable for Quad {
         let beta = dot(self.w, cross(self.u, planar_hit_point));
 
         // Check if the hit point lies inside the quad
-        if alpha < 0.0 || alpha > 1.0 || beta < 0.0 || beta > 1.0 {
+        if !(0.0..=1.0).contains(&alpha) || !(0.0..=1.0).contains(&beta) {
             return None;
         }
 
@@ -101,10 +110,35 @@ impl Hittable for Quad {
             mat: self.mat.clone(),
             normal: Default::default(),
             front_face: Default::default(),
+            uv: UV::new(alpha, beta),
         };
 
         rec.set_face_normal(ray, self.normal);
 
         Some(rec)
     }
+
+    fn bounding_box(&self) -> Aabb {
+        let q = self.q;
+        Aabb::from_points(&[q, q + self.u, q + self.v, q + self.u + self.v])
+    }
+
+    /// Returns the density of the directions towards the quad picked by [`Hittable::random`].
+    ///
+    /// Points are picked uniformly over the area of the quad, a density of `1 / area`,
+    /// which becomes `distance² / (cos θ × area)` per unit of solid angle seen from `origin`.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let Some(rec) = self.hit(&Ray::new(origin, direction), 0.001, common::INFINITY) else {
+            return 0.0;
+        };
+        let distance_squared = rec.t * rec.t * direction.length_squared();
+        let cosine = f64::abs(dot(direction, self.normal)) / direction.length();
+        distance_squared / (cosine * self.area)
+    }
+
+    /// Returns the direction from `origin` to a random point of the quad.
+    fn random(&self, origin: Point3) -> Vec3 {
+        let p = self.q + common::random_double() * self.u + common::random_double() * self.v;
+        p - origin
+    }
 }
@@ -0,0 +1,491 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use rayon::prelude::*;
+
+use crate::aov::Aov;
+use crate::camera::Camera;
+use crate::color::Color;
+use crate::common;
+use crate::framebuffer::FrameBuffer;
+use crate::hittable::{HitRecord, Hittable};
+use crate::hittable_list::HittableList;
+use crate::material;
+use crate::ray::Ray;
+use crate::vec3::{self, Point3, Vec3};
+
+/// What a ray sees when it escapes the scene without hitting anything.
+#[derive(Debug, Clone, Copy)]
+pub enum Background {
+    /// A vertical gradient from white at the horizon to light blue at the zenith.
+    Sky,
+    /// A uniform color. Black makes the scene dark unless it is lit by its materials.
+    Solid(Color),
+}
+
+impl Background {
+    /// Returns the color of the background in the direction of `r`.
+    pub fn color(&self, r: &Ray) -> Color {
+        match self {
+            Background::Sky => {
+                let unit_direction = vec3::unit_vector(r.direction());
+                let t = 0.5 * (unit_direction.y() + 1.0);
+                (1.0 - t) * Color::new(1.0, 1.0, 1.0) + t * Color::new(0.5, 0.7, 1.0)
+            }
+            Background::Solid(color) => *color,
+        }
+    }
+}
+
+/// Settings of a [`Renderer`], built with chained setters or by setting the fields.
+///
+/// # Example
+///
+/// ```
+/// use raytracer::{Background, Color, RenderSettings};
+///
+/// let settings = RenderSettings::new()
+///     .resolution(300, 200)
+///     .samples_per_pixel(16)
+///     .max_depth(10)
+///     .background(Background::Solid(Color::new(0.0, 0.0, 0.0)));
+/// assert_eq!(settings.width, 300);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RenderSettings {
+    /// Width of the image in pixels.
+    pub width: usize,
+    /// Height of the image in pixels.
+    pub height: usize,
+    /// Number of rays traced through every pixel.
+    pub samples_per_pixel: u32,
+    /// Maximum number of times a ray may bounce.
+    pub max_depth: u32,
+    /// What rays escaping the scene see.
+    pub background: Background,
+    /// Seed of the random generator, `None` for a different image on every render.
+    pub seed: Option<u64>,
+    /// What the camera rays see at their first hit, rendered along with the image.
+    pub aovs: Vec<Aov>,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        RenderSettings {
+            width: 600,
+            height: 400,
+            samples_per_pixel: 100,
+            max_depth: 25,
+            background: Background::Sky,
+            seed: None,
+            aovs: Vec::new(),
+        }
+    }
+}
+
+impl RenderSettings {
+    /// Creates the default settings: 600x400 pixels, 100 samples per pixel,
+    /// 25 bounces, a sky background and no AOVs.
+    pub fn new() -> RenderSettings {
+        Default::default()
+    }
+
+    /// Sets the size of the image in pixels.
+    pub fn resolution(mut self, width: usize, height: usize) -> Self {
+        self.width = width;
+        self.height = height;
+        self
+    }
+
+    /// Sets the number of rays traced through every pixel.
+    pub fn samples_per_pixel(mut self, samples_per_pixel: u32) -> Self {
+        self.samples_per_pixel = samples_per_pixel;
+        self
+    }
+
+    /// Sets the maximum number of times a ray may bounce.
+    pub fn max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = max_depth;
+        self
+    }
+
+    /// Sets what rays escaping the scene see.
+    pub fn background(mut self, background: Background) -> Self {
+        self.background = background;
+        self
+    }
+
+    /// Makes the render reproducible: the same seed gives the same image,
+    /// whatever the number of threads.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    /// Renders `aovs` along with the image. They do not change its colors.
+    pub fn aovs(mut self, aovs: &[Aov]) -> Self {
+        self.aovs = aovs.to_vec();
+        self
+    }
+}
+
+/// Renders scenes into [`FrameBuffer`]s, using every thread of the rayon pool.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use raytracer::*;
+///
+/// let mut world = HittableList::new();
+/// let red = Arc::new(Lambertian::new(Color::new(0.9, 0.2, 0.3)));
+/// world.add(Box::new(Sphere::new(Point3::new(0.0, 0.0, 0.0), 0.5, red)));
+/// let cam = Camera::new(
+///     Point3::new(0.0, 0.0, 3.0),
+///     Point3::new(0.0, 0.0, 0.0),
+///     Vec3::new(0.0, 1.0, 0.0),
+///     40.0,
+///     1.0,
+///     0.0,
+///     3.0,
+/// );
+///
+/// let renderer = Renderer::new(RenderSettings::new().resolution(16, 16).samples_per_pixel(4));
+/// let image = renderer.render(&world, &cam);
+/// assert_eq!(image.width(), 16);
+/// ```
+pub struct Renderer {
+    settings: RenderSettings,
+    /// Objects rays are sent towards directly, see [`Renderer::with_lights`].
+    lights: HittableList,
+}
+
+impl Renderer {
+    /// Creates a renderer using the given settings.
+    pub fn new(settings: RenderSettings) -> Renderer {
+        Renderer {
+            settings,
+            lights: HittableList::new(),
+        }
+    }
+
+    /// Samples `lights` directly at every diffuse bounce.
+    ///
+    /// Without it, a ray only gets light when it happens to bounce into a light,
+    /// which leaves scenes lit by small lights very noisy. With it, every bounce also
+    /// sends a shadow ray towards a random point of a light, and both estimates
+    /// are combined by multiple importance sampling, each weighted by how likely
+    /// it was to find that light.
+    ///
+    /// `lights` holds copies of emissive objects of the world that can be sampled,
+    /// such as [`Quad`](crate::Quad)s, [`Sphere`](crate::Sphere)s and [`Disk`](crate::Disk)s.
+    /// They are only used to pick directions, what is seen in that direction is
+    /// still found in the world.
+    ///
+    /// # Example
+    ///
+    /// ```
+    /// use std::sync::Arc;
+    /// use raytracer::*;
+    ///
+    /// let lamp = Arc::new(Sphere::new(
+    ///     Point3::new(0.0, 2.0, 0.0),
+    ///     0.25,
+    ///     Arc::new(DiffuseLight::new(Color::new(20.0, 20.0, 20.0))),
+    /// ));
+    /// let mut world = HittableList::new();
+    /// world.add(Box::new(lamp.clone()));
+    /// let mut lights = HittableList::new();
+    /// lights.add(Box::new(lamp));
+    ///
+    /// let renderer = Renderer::new(RenderSettings::new()).with_lights(lights);
+    /// ```
+    pub fn with_lights(mut self, lights: HittableList) -> Renderer {
+        self.lights = lights;
+        self
+    }
+
+    /// The settings of the renderer.
+    pub fn settings(&self) -> &RenderSettings {
+        &self.settings
+    }
+
+    /// Renders `world` as seen from `camera`.
+    pub fn render(&self, world: &dyn Hittable, camera: &Camera) -> FrameBuffer {
+        self.render_with_progress(world, camera, |_, _| {})
+    }
+
+    /// Renders `world` as seen from `camera`, calling `progress` with the number of
+    /// rows done and the total number of rows each time a row is finished.
+    ///
+    /// `progress` is called from the worker threads, rows may finish in any order.
+    pub fn render_with_progress<F>(&self, world: &dyn Hittable, camera: &Camera, progress: F) -> FrameBuffer
+    where
+        F: Fn(usize, usize) + Sync,
+    {
+        let (width, height) = (self.settings.width, self.settings.height);
+        let rows_done = AtomicUsize::new(0);
+
+        let rows: Vec<Vec<Pixel>> = (0..height)
+            .into_par_iter()
+            .map(|row| {
+                // Row 0 is the top of the image, where the camera's v coordinate is 1
+                let j = height - 1 - row;
+                let line = (0..width).map(|i| self.render_pixel(world, camera, i, j)).collect();
+                progress(rows_done.fetch_add(1, Ordering::Relaxed) + 1, height);
+                line
+            })
+            .collect();
+
+        let pixels = rows.iter().flatten().map(|pixel| pixel.color).collect();
+        let alpha = rows.iter().flatten().map(|pixel| pixel.alpha).collect();
+        let mut image = FrameBuffer::from_pixels(width, height, pixels).with_alpha(alpha);
+
+        let mut offset = 0;
+        for &aov in &self.settings.aovs {
+            let components = aov.components();
+            let values = if aov == Aov::MaterialId {
+                material_ids(rows.iter().flatten())
+            } else {
+                rows.iter()
+                    .flatten()
+                    .flat_map(|pixel| &pixel.aovs[offset..offset + components])
+                    .copied()
+                    .collect()
+            };
+            image = image.with_aov(aov, values);
+            offset += components;
+        }
+        image
+    }
+
+    /// Averages the samples of the pixel at column `i` and row `j`, counted from the bottom.
+    fn render_pixel(&self, world: &dyn Hittable, camera: &Camera, i: usize, j: usize) -> Pixel {
+        let (width, height) = (self.settings.width, self.settings.height);
+
+        // Each pixel gets its own sequence, so the image does not depend on scheduling
+        if let Some(seed) = self.settings.seed {
+            let index = (j * width + i) as u64;
+            common::seed_random(seed ^ index.wrapping_mul(0x9E37_79B9_7F4A_7C15));
+        }
+
+        let mut pixel_color = Color::new(0.0, 0.0, 0.0);
+        let mut hits = 0;
+        let mut first_hits = FirstHits::default();
+        for _ in 0..self.settings.samples_per_pixel {
+            let u = ((i as f64) + common::random_double()) / width.saturating_sub(1).max(1) as f64;
+            let v = ((j as f64) + common::random_double()) / height.saturating_sub(1).max(1) as f64;
+            let r = camera.get_ray(u, v);
+            let hit = world.hit(&r, 0.001, common::INFINITY);
+            hits += hit.is_some() as u32;
+            if !self.settings.aovs.is_empty() {
+                first_hits.add(&r, hit.as_ref(), &self.settings.background);
+            }
+            pixel_color += self.shade(&r, hit, world, self.settings.max_depth, None);
+        }
+        let samples = self.settings.samples_per_pixel.max(1) as f64;
+        Pixel {
+            color: pixel_color / samples,
+            alpha: hits as f64 / samples,
+            aovs: first_hits.values(&self.settings.aovs),
+            material: first_hits.material,
+        }
+    }
+
+    /// Returns the light coming back along `r`.
+    ///
+    /// `scattering_pdf` is the density with which the previous bounce picked `r`,
+    /// `None` for camera rays and rays leaving a delta lobe, like a mirror, whose
+    /// light could not have been found by sampling the lights.
+    fn ray_color(&self, r: &Ray, world: &dyn Hittable, depth: u32, scattering_pdf: Option<f64>) -> Color {
+        if depth == 0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        self.shade(r, world.hit(r, 0.001, common::INFINITY), world, depth, scattering_pdf)
+    }
+
+    /// Returns the light coming back along `r`, whose closest hit is `hit`.
+    fn shade(
+        &self,
+        r: &Ray,
+        hit: Option<HitRecord>,
+        world: &dyn Hittable,
+        depth: u32,
+        scattering_pdf: Option<f64>,
+    ) -> Color {
+        if depth == 0 {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        if let Some(hit_rec) = hit {
+            let mut emitted = hit_rec.mat.emitted(r, &hit_rec);
+            if let Some(pdf) = scattering_pdf {
+                if !emitted.near_zero() {
+                    // This light may also have been reached by a shadow ray from the previous bounce
+                    emitted *= power_heuristic(pdf, self.lights.pdf_value(r.origin(), r.direction()));
+                }
+            }
+
+            let wo = -vec3::unit_vector(r.direction());
+            let direct = if depth > 1 {
+                self.sample_lights(&hit_rec, wo, world)
+            } else {
+                Color::new(0.0, 0.0, 0.0)
+            };
+
+            let Some(sample) = hit_rec.mat.sample(&hit_rec, wo, material::random_numbers()) else {
+                return emitted + direct;
+            };
+            let scattered = Ray::new(hit_rec.p, sample.wi);
+            let pdf = (!sample.is_delta).then_some(sample.pdf);
+            return emitted + direct + sample.weight * self.ray_color(&scattered, world, depth - 1, pdf);
+        }
+
+        self.settings.background.color(r)
+    }
+
+    /// Returns the light reaching the hit point `rec` through a shadow ray sent
+    /// towards a random point of the lights, and reflected towards `wo`.
+    fn sample_lights(&self, rec: &HitRecord, wo: Vec3, world: &dyn Hittable) -> Color {
+        if self.lights.is_empty() {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        let wi = vec3::unit_vector(self.lights.random(rec.p));
+        let f = rec.mat.eval(rec, wo, wi);
+        if f.near_zero() {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+        let light_pdf = self.lights.pdf_value(rec.p, wi);
+        if light_pdf <= 0.0 || !light_pdf.is_finite() {
+            return Color::new(0.0, 0.0, 0.0);
+        }
+
+        // Whatever the shadow ray hits first is what lights the point, nothing if it is not emissive
+        let shadow_ray = Ray::new(rec.p, wi);
+        let Some(light_rec) = world.hit(&shadow_ray, 0.001, common::INFINITY) else {
+            return Color::new(0.0, 0.0, 0.0);
+        };
+        let emitted = light_rec.mat.emitted(&shadow_ray, &light_rec);
+        let cosine = f64::abs(vec3::dot(wi, rec.normal));
+        let weight = power_heuristic(light_pdf, rec.mat.pdf(rec, wo, wi));
+        f * emitted * (cosine * weight / light_pdf)
+    }
+}
+
+/// What the samples of a pixel saw.
+struct Pixel {
+    color: Color,
+    /// The fraction of the samples that hit an object.
+    alpha: f64,
+    /// The components of the AOVs of the settings, one after the other. The material ID
+    /// only holds a place, it is numbered once the whole image is known.
+    aovs: Vec<f64>,
+    /// The material hit by the first sample, identified by its address.
+    material: Option<usize>,
+}
+
+/// The sums of what the camera rays of a pixel saw at their first hit.
+#[derive(Default)]
+struct FirstHits {
+    samples: u32,
+    hits: u32,
+    albedo: Color,
+    normal: Vec3,
+    position: Point3,
+    depth: f64,
+    uv: (f64, f64),
+    material: Option<usize>,
+}
+
+impl FirstHits {
+    /// Adds the camera ray `r`, whose closest hit is `hit`.
+    fn add(&mut self, r: &Ray, hit: Option<&HitRecord>, background: &Background) {
+        self.samples += 1;
+        let Some(rec) = hit else {
+            self.albedo += background.color(r);
+            return;
+        };
+        if self.samples == 1 {
+            self.material = Some(Arc::as_ptr(&rec.mat) as *const () as usize);
+        }
+        self.hits += 1;
+        self.albedo += rec.mat.albedo(rec);
+        self.normal += rec.normal;
+        self.position += rec.p;
+        // Camera rays are not normalized, t is only a distance along unit directions
+        self.depth += rec.t * r.direction().length();
+        self.uv.0 += rec.uv.x();
+        self.uv.1 += rec.uv.y();
+    }
+
+    /// The averages of `aovs`, their components one after the other.
+    ///
+    /// The albedo is averaged over every sample, the background included. The other
+    /// AOVs only over the samples that hit something, and are 0 if none did, except the
+    /// depth, which is infinite. The average normal is scaled back to unit length.
+    fn values(&self, aovs: &[Aov]) -> Vec<f64> {
+        let samples = self.samples.max(1) as f64;
+        let hits = self.hits.max(1) as f64;
+        let mut values = Vec::new();
+        for aov in aovs {
+            match aov {
+                Aov::Albedo => {
+                    let albedo = self.albedo / samples;
+                    values.extend([albedo.x(), albedo.y(), albedo.z()]);
+                }
+                Aov::Normal => {
+                    // Normals seen by the samples of an edge cancel out in part
+                    let normal = if self.normal.near_zero() {
+                        Vec3::default()
+                    } else {
+                        vec3::unit_vector(self.normal)
+                    };
+                    values.extend([normal.x(), normal.y(), normal.z()]);
+                }
+                Aov::Position => {
+                    let position = self.position / hits;
+                    values.extend([position.x(), position.y(), position.z()]);
+                }
+                Aov::Depth if self.hits == 0 => values.push(f64::INFINITY),
+                Aov::Depth => values.push(self.depth / hits),
+                Aov::Uv => values.extend([self.uv.0 / hits, self.uv.1 / hits]),
+                Aov::MaterialId => values.push(0.0),
+            }
+        }
+        values
+    }
+}
+
+/// Numbers the materials hit by the first sample of `pixels` from 1, in the order they
+/// first appear, leaving 0 to the pixels whose first sample missed.
+fn material_ids<'a>(pixels: impl Iterator<Item = &'a Pixel>) -> Vec<f64> {
+    let mut ids = HashMap::new();
+    pixels
+        .map(|pixel| match pixel.material {
+            Some(material) => {
+                let next = ids.len() + 1;
+                *ids.entry(material).or_insert(next) as f64
+            }
+            None => 0.0,
+        })
+        .collect()
+}
+
+/// The weight of a sample picked with density `pdf`, when the same light could
+/// also have been found with density `other` by another strategy.
+///
+/// Squaring the densities favors the strategy most likely to find the light more
+/// strongly than their plain ratio would, which removes more noise.
+fn power_heuristic(pdf: f64, other: f64) -> f64 {
+    if other.is_infinite() {
+        return 0.0;
+    }
+    let (a, b) = (pdf * pdf, other * other);
+    if a + b == 0.0 {
+        1.0
+    } else {
+        a / (a + b)
+    }
+}
@@ -0,0 +1,44 @@
+use crate::camera::Camera;
+use crate::hittable_list::HittableList;
+use crate::renderer::Background;
+
+/// A complete scene ready to be rendered: the objects of the world, the
+/// camera looking at them and what lies beyond them.
+///
+/// Scenes are usually built by hand or loaded from a JSON description with
+/// [`crate::parsing::load_scene`].
+pub struct Scene {
+    /// Every object of the scene.
+    pub world: HittableList,
+    /// The camera the scene is seen through.
+    pub camera: Camera,
+    /// What rays escaping the world see, the sky unless set otherwise.
+    pub background: Background,
+    /// Copies of the lights of the world that rays are sent towards directly,
+    /// see [`Renderer::with_lights`](crate::Renderer::with_lights).
+    pub lights: HittableList,
+}
+
+impl Scene {
+    /// Creates a new scene from a world and a camera, under the sky.
+    pub fn new(world: HittableList, camera: Camera) -> Scene {
+        Scene {
+            world,
+            camera,
+            background: Background::Sky,
+            lights: HittableList::new(),
+        }
+    }
+
+    /// Replaces the sky by `background`, for example black for a scene lit only by its lights.
+    pub fn with_background(mut self, background: Background) -> Scene {
+        self.background = background;
+        self
+    }
+
+    /// Sets the lights sampled directly by the renderer, which must also be in the world.
+    pub fn with_lights(mut self, lights: HittableList) -> Scene {
+        self.lights = lights;
+        self
+    }
+}
@@ -1,9 +1,12 @@
 use std::sync::Arc;
 
+use crate::aabb::Aabb;
+use crate::common::{self, PI};
 use crate::hittable::{HitRecord, Hittable};
 use crate::material::Material;
 use crate::ray::Ray;
-use crate::vec3::{self, Point3};
+use crate::vec2::UV;
+use crate::vec3::{self, Point3, Vec3};
 
 /// Represents a sphere in 3D space.
 /// 
@@ -28,6 +31,18 @@ impl Sphere {
             mat: m,
         }
     }
+
+    /// Returns the texture coordinates of the point `p` of a unit sphere centered at the origin.
+    ///
+    /// `u` is the longitude, going around the Y axis from X = -1, and `v` the
+    /// latitude, from the bottom pole at 0 to the top pole at 1.
+    ///
+    /// **For beginners:** This is how a world map is wrapped around a globe.
+    fn uv(p: Point3) -> UV {
+        let theta = f64::acos(-p.y());
+        let phi = f64::atan2(-p.z(), p.x()) + PI;
+        UV::new(phi / (2.0 * PI), theta / PI)
+    }
 }
 
 impl Hittable for Sphere {
@@ -59,15 +74,57 @@ impl Hittable for Sphere {
             }
         }
 
+        let p = r.at(root);
+        let outward_normal = (p - self.center) / self.radius;
         let mut rec = HitRecord {
             t: root,
-            p: r.at(root),
+            p,
             mat: self.mat.clone(),
             normal: Default::default(),
             front_face: Default::default(),
+            uv: Sphere::uv(outward_normal),
         };
-        let outward_normal = (rec.p - self.center) / self.radius;
         rec.set_face_normal(r, outward_normal);
         Some(rec)
     }
+
+    fn bounding_box(&self) -> Aabb {
+        let rvec = Vec3::new(self.radius, self.radius, self.radius);
+        Aabb::new(self.center - rvec, self.center + rvec)
+    }
+
+    /// Returns the density of the directions towards the sphere picked by [`Hittable::random`].
+    ///
+    /// Directions are picked uniformly in the cone the sphere fills when seen from
+    /// `origin`, so the density is one over the solid angle of that cone.
+    /// It is 0 from inside the sphere, which is then never sampled.
+    fn pdf_value(&self, origin: Point3, direction: Vec3) -> f64 {
+        let distance_squared = (self.center - origin).length_squared();
+        if distance_squared <= self.radius * self.radius
+            || self.hit(&Ray::new(origin, direction), 0.001, common::INFINITY).is_none()
+        {
+            return 0.0;
+        }
+        let cos_theta_max = f64::sqrt(1.0 - self.radius * self.radius / distance_squared);
+        let solid_angle = 2.0 * PI * (1.0 - cos_theta_max);
+        1.0 / solid_angle
+    }
+
+    /// Returns a random direction from `origin` in the cone the sphere fills.
+    fn random(&self, origin: Point3) -> Vec3 {
+        let w = self.center - origin;
+        let distance_squared = w.length_squared();
+        if distance_squared <= self.radius * self.radius {
+            return vec3::random_unit_vector();
+        }
+
+        let cos_theta_max = f64::sqrt(1.0 - self.radius * self.radius / distance_squared);
+        let phi = 2.0 * PI * common::random_double();
+        let z = 1.0 + common::random_double() * (cos_theta_max - 1.0);
+        let sin_theta = f64::sqrt(1.0 - z * z);
+
+        let w = vec3::unit_vector(w);
+        let (u, v) = vec3::orthonormal_basis(w);
+        sin_theta * f64::cos(phi) * u + sin_theta * f64::sin(phi) * v + z * w
+    }
 }
\ No newline at end of file
@@ -0,0 +1,393 @@
+use std::fmt;
+use std::fs;
+use std::io::Cursor;
+use std::path::{Path, PathBuf};
+
+use super::texture::Texture;
+use crate::{Color, Point3};
+
+/// How texture coordinates outside of `[0, 1]` are brought back onto the image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WrapMode {
+    /// The image is tiled endlessly.
+    #[default]
+    Repeat,
+    /// The texels on the border of the image are stretched outwards.
+    Clamp,
+    /// The image is tiled, every other tile flipped so that the tiles meet seamlessly.
+    Mirror,
+}
+
+/// How the color between the centers of texels is found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Filter {
+    /// The color of the closest texel, which shows square texels when magnified.
+    Nearest,
+    /// A blend of the four closest texels, weighted by their distance.
+    #[default]
+    Bilinear,
+}
+
+/// A texture looked up in an image by texture coordinates.
+///
+/// `u` runs from the left to the right of the image and `v` from its bottom to
+/// its top. Images are decoded from PPM (P3 and P6), PNG or JPEG files; their
+/// texels are assumed to be sRGB encoded, as in nearly every image, and are
+/// converted to linear colors once when loaded. Transparency is ignored.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use raytracer::{Filter, ImageTexture, Lambertian, Point3, Texture, WrapMode};
+///
+/// // A 2x1 image, black on the left and white on the right
+/// let image = ImageTexture::from_srgb8(2, 1, &[0, 0, 0, 255, 255, 255])
+///     .wrap(WrapMode::Clamp)
+///     .filter(Filter::Nearest);
+/// assert_eq!(image.get_color(0.9, 0.5, &Point3::default()).x(), 1.0);
+/// let material = Lambertian::from_texture(Arc::new(image));
+/// ```
+pub struct ImageTexture {
+    width: usize,
+    height: usize,
+    /// Linear colors of the texels, row by row from the top of the image.
+    texels: Vec<[f32; 3]>,
+    wrap: WrapMode,
+    filter: Filter,
+}
+
+impl ImageTexture {
+    /// Reads the image at `path`, in PPM, PNG or JPEG format.
+    ///
+    /// The format is recognized from the content of the file, whatever its extension.
+    /// The texture repeats and is filtered bilinearly, see [`ImageTexture::wrap`] and
+    /// [`ImageTexture::filter`] to change it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an [`ImageError`] if the file cannot be read, is in another format
+    /// or is malformed.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<ImageTexture, ImageError> {
+        let path = path.as_ref();
+        let data = fs::read(path).map_err(|error| ImageError::Io {
+            path: path.to_path_buf(),
+            error,
+        })?;
+
+        let decoded = if data.starts_with(b"P3") || data.starts_with(b"P6") {
+            decode_ppm(&data)
+        } else if data.starts_with(b"\x89PNG") {
+            decode_png(&data)
+        } else if data.starts_with(&[0xFF, 0xD8]) {
+            decode_jpeg(&data)
+        } else {
+            Err("unsupported image format, expected PPM, PNG or JPEG".to_string())
+        };
+
+        let (width, height, texels) = decoded.map_err(|message| ImageError::Format {
+            path: path.to_path_buf(),
+            message,
+        })?;
+        if width == 0 || height == 0 {
+            return Err(ImageError::Format {
+                path: path.to_path_buf(),
+                message: "the image is empty".to_string(),
+            });
+        }
+        Ok(ImageTexture::new(width, height, texels))
+    }
+
+    /// Creates a texture from sRGB encoded 8-bit texels, three bytes per texel,
+    /// row by row from the top of the image.
+    ///
+    /// # Panics
+    /// If the image is empty or `rgb` does not hold `width × height` texels.
+    pub fn from_srgb8(width: usize, height: usize, rgb: &[u8]) -> ImageTexture {
+        assert_eq!(rgb.len(), width * height * 3, "an image needs three bytes per texel");
+        let lut = srgb8_to_linear();
+        let texels = rgb
+            .chunks_exact(3)
+            .map(|texel| [lut[texel[0] as usize], lut[texel[1] as usize], lut[texel[2] as usize]])
+            .collect();
+        ImageTexture::new(width, height, texels)
+    }
+
+    /// Creates a texture from linear colors, row by row from the top of the image.
+    ///
+    /// # Panics
+    /// If the image is empty or `colors` does not hold `width × height` texels.
+    pub fn from_colors(width: usize, height: usize, colors: &[Color]) -> ImageTexture {
+        assert_eq!(colors.len(), width * height, "an image needs one color per texel");
+        let texels = colors
+            .iter()
+            .map(|c| [c.x() as f32, c.y() as f32, c.z() as f32])
+            .collect();
+        ImageTexture::new(width, height, texels)
+    }
+
+    fn new(width: usize, height: usize, texels: Vec<[f32; 3]>) -> ImageTexture {
+        assert!(width > 0 && height > 0, "an image needs at least one texel");
+        ImageTexture {
+            width,
+            height,
+            texels,
+            wrap: WrapMode::default(),
+            filter: Filter::default(),
+        }
+    }
+
+    /// Sets how the texture coordinates outside of `[0, 1]` are handled.
+    pub fn wrap(mut self, wrap: WrapMode) -> Self {
+        self.wrap = wrap;
+        self
+    }
+
+    /// Sets how the texels are blended.
+    pub fn filter(mut self, filter: Filter) -> Self {
+        self.filter = filter;
+        self
+    }
+
+    /// The width of the image in texels.
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    /// The height of the image in texels.
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    /// The color of the texel at column `i` and row `j`, which may lie outside the
+    /// image and are wrapped back onto it.
+    fn texel(&self, i: i64, j: i64) -> Color {
+        let i = wrap_index(i, self.width, self.wrap);
+        let j = wrap_index(j, self.height, self.wrap);
+        let [r, g, b] = self.texels[j * self.width + i];
+        Color::new(r as f64, g as f64, b as f64)
+    }
+}
+
+impl Texture for ImageTexture {
+    fn get_color(&self, u: f64, v: f64, _point: &Point3) -> Color {
+        // Malformed coordinates fall on the corner rather than anywhere
+        let u = if u.is_finite() { u } else { 0.0 };
+        let v = if v.is_finite() { v } else { 0.0 };
+
+        // Texel (i, j) covers [i, i + 1) x [j, j + 1), rows start from the top
+        let x = u * self.width as f64;
+        let y = (1.0 - v) * self.height as f64;
+        match self.filter {
+            Filter::Nearest => self.texel(x.floor() as i64, y.floor() as i64),
+            Filter::Bilinear => {
+                // Measured from the centers of the texels
+                let (x, y) = (x - 0.5, y - 0.5);
+                let (i, j) = (x.floor(), y.floor());
+                let (fx, fy) = (x - i, y - j);
+                let (i, j) = (i as i64, j as i64);
+                let top = (1.0 - fx) * self.texel(i, j) + fx * self.texel(i + 1, j);
+                let bottom = (1.0 - fx) * self.texel(i, j + 1) + fx * self.texel(i + 1, j + 1);
+                (1.0 - fy) * top + fy * bottom
+            }
+        }
+    }
+}
+
+/// Brings the index `i` of a row or column back into an image `size` texels wide.
+fn wrap_index(i: i64, size: usize, wrap: WrapMode) -> usize {
+    let size = size as i64;
+    let i = match wrap {
+        WrapMode::Repeat => i.rem_euclid(size),
+        WrapMode::Clamp => i.clamp(0, size - 1),
+        WrapMode::Mirror => {
+            let i = i.rem_euclid(2 * size);
+            if i < size {
+                i
+            } else {
+                2 * size - 1 - i
+            }
+        }
+    };
+    i as usize
+}
+
+/// Converts an sRGB encoded value in `[0, 1]` to a linear one.
+fn srgb_to_linear(c: f32) -> f32 {
+    if c <= 0.04045 {
+        c / 12.92
+    } else {
+        ((c + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+/// The linear value of every 8-bit sRGB encoded value.
+fn srgb8_to_linear() -> [f32; 256] {
+    std::array::from_fn(|i| srgb_to_linear(i as f32 / 255.0))
+}
+
+/// The decoded size and linear texels of an image, or why it could not be decoded.
+type Decoded = Result<(usize, usize, Vec<[f32; 3]>), String>;
+
+/// Decodes a PPM image, either plain (P3) or raw (P6), of any maximum value.
+fn decode_ppm(data: &[u8]) -> Decoded {
+    let mut pos = 0;
+    let magic = ppm_token(data, &mut pos).ok_or("missing PPM header")?;
+    if magic != b"P3" && magic != b"P6" {
+        return Err(format!(
+            "expected a P3 or P6 PPM image, found \"{}\"",
+            String::from_utf8_lossy(magic)
+        ));
+    }
+    let mut header = [0; 3];
+    for (value, name) in header.iter_mut().zip(["width", "height", "maximum value"]) {
+        let token = ppm_token(data, &mut pos).ok_or_else(|| format!("missing {}", name))?;
+        *value = std::str::from_utf8(token)
+            .ok()
+            .and_then(|s| s.parse::<usize>().ok())
+            .ok_or_else(|| format!("expected the {}, found \"{}\"", name, String::from_utf8_lossy(token)))?;
+    }
+    let [width, height, max] = header;
+    if max == 0 || max > 65535 {
+        return Err(format!("the maximum value must be from 1 to 65535, not {}", max));
+    }
+    let count = width
+        .checked_mul(height)
+        .and_then(|n| n.checked_mul(3))
+        .ok_or("the image is too large")?;
+
+    let samples: Vec<usize> = if magic == b"P3" {
+        (0..count)
+            .map(|_| {
+                let token = ppm_token(data, &mut pos).ok_or("the image is truncated")?;
+                std::str::from_utf8(token)
+                    .ok()
+                    .and_then(|s| s.parse::<usize>().ok())
+                    .filter(|&sample| sample <= max)
+                    .ok_or_else(|| format!("invalid sample \"{}\"", String::from_utf8_lossy(token)))
+            })
+            .collect::<Result<_, _>>()?
+    } else {
+        // A single whitespace separates the header from the samples
+        let start = pos + 1;
+        let bytes = if max < 256 { 1 } else { 2 };
+        let end = count
+            .checked_mul(bytes)
+            .and_then(|len| start.checked_add(len))
+            .ok_or("the image is too large")?;
+        let raw = data.get(start..end).ok_or("the image is truncated")?;
+        if bytes == 1 {
+            raw.iter().map(|&b| b as usize).collect()
+        } else {
+            raw.chunks_exact(2).map(|b| u16::from_be_bytes([b[0], b[1]]) as usize).collect()
+        }
+    };
+
+    let linear = |sample: usize| srgb_to_linear(sample as f32 / max as f32);
+    let texels = samples
+        .chunks_exact(3)
+        .map(|s| [linear(s[0]), linear(s[1]), linear(s[2])])
+        .collect();
+    Ok((width, height, texels))
+}
+
+/// Reads the next token of a PPM header, skipping whitespace and `#` comments.
+fn ppm_token<'d>(data: &'d [u8], pos: &mut usize) -> Option<&'d [u8]> {
+    loop {
+        match data.get(*pos)? {
+            b'#' => {
+                while data.get(*pos).is_some_and(|&b| b != b'\n') {
+                    *pos += 1;
+                }
+            }
+            b if b.is_ascii_whitespace() => *pos += 1,
+            _ => break,
+        }
+    }
+    let start = *pos;
+    while data.get(*pos).is_some_and(|b| !b.is_ascii_whitespace()) {
+        *pos += 1;
+    }
+    Some(&data[start..*pos])
+}
+
+/// Decodes a PNG image, of any color type and bit depth.
+fn decode_png(data: &[u8]) -> Decoded {
+    let mut decoder = png::Decoder::new(Cursor::new(data));
+    // Palettes are expanded and 16-bit samples reduced, leaving 8-bit gray or RGB
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|err| err.to_string())?;
+    let size = reader.output_buffer_size().ok_or("the image is too large")?;
+    let mut buf = vec![0; size];
+    let info = reader.next_frame(&mut buf).map_err(|err| err.to_string())?;
+
+    let channels = match info.color_type {
+        png::ColorType::Grayscale => 1,
+        png::ColorType::GrayscaleAlpha => 2,
+        png::ColorType::Rgb => 3,
+        png::ColorType::Rgba => 4,
+        png::ColorType::Indexed => return Err("unexpected indexed colors".to_string()),
+    };
+    let (width, height) = (info.width as usize, info.height as usize);
+    let lut = srgb8_to_linear();
+    let mut texels = Vec::with_capacity(width * height);
+    for row in buf.chunks(info.line_size).take(height) {
+        for texel in row.chunks_exact(channels).take(width) {
+            texels.push(match channels {
+                1 | 2 => [lut[texel[0] as usize]; 3],
+                _ => [lut[texel[0] as usize], lut[texel[1] as usize], lut[texel[2] as usize]],
+            });
+        }
+    }
+    Ok((width, height, texels))
+}
+
+/// Decodes a grayscale or color JPEG image.
+fn decode_jpeg(data: &[u8]) -> Decoded {
+    let mut decoder = jpeg_decoder::Decoder::new(Cursor::new(data));
+    let pixels = decoder.decode().map_err(|err| err.to_string())?;
+    let info = decoder.info().ok_or("missing image information")?;
+    let (width, height) = (info.width as usize, info.height as usize);
+
+    let lut = srgb8_to_linear();
+    let texels = match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => pixels.iter().map(|&l| [lut[l as usize]; 3]).collect(),
+        jpeg_decoder::PixelFormat::L16 => pixels
+            .chunks_exact(2)
+            .map(|l| [srgb_to_linear(u16::from_ne_bytes([l[0], l[1]]) as f32 / 65535.0); 3])
+            .collect(),
+        jpeg_decoder::PixelFormat::RGB24 => pixels
+            .chunks_exact(3)
+            .map(|c| [lut[c[0] as usize], lut[c[1] as usize], lut[c[2] as usize]])
+            .collect(),
+        jpeg_decoder::PixelFormat::CMYK32 => return Err("CMYK images are not supported".to_string()),
+    };
+    Ok((width, height, texels))
+}
+
+/// Error returned when an image cannot be loaded.
+#[derive(Debug)]
+pub enum ImageError {
+    /// The file could not be read.
+    Io { path: PathBuf, error: std::io::Error },
+    /// The file is not an image in a supported format, or is malformed.
+    Format { path: PathBuf, message: String },
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImageError::Io { path, error } => write!(f, "cannot read {}: {}", path.display(), error),
+            ImageError::Format { path, message } => write!(f, "{}: {}", path.display(), message),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ImageError::Io { error, .. } => Some(error),
+            ImageError::Format { .. } => None,
+        }
+    }
+}
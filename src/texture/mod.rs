@@ -1,7 +1,14 @@
 #![allow(unused_imports)]
 
+#[allow(clippy::module_inception)]
 pub mod texture;
 pub mod solid;
 pub mod checker;
+pub mod image;
+pub mod noise;
 
-pub use texture::*;
\ No newline at end of file
+pub use texture::*;
+pub use solid::SolidColor;
+pub use checker::CheckerTexture;
+pub use image::{Filter, ImageError, ImageTexture, WrapMode};
+pub use noise::{ColorRamp, MarbleTexture, NoiseTexture, Perlin, WoodTexture};
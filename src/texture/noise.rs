@@ -0,0 +1,385 @@
+use rand::rngs::SmallRng;
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+
+use super::texture::Texture;
+use crate::common::PI;
+use crate::vec3::dot;
+use crate::{Color, Point3, Vec3};
+
+/// Number of lattice gradients, the noise repeats every `POINT_COUNT` units.
+const POINT_COUNT: usize = 256;
+
+/// Gradient noise, as described by Ken Perlin.
+///
+/// Random unit gradients are placed on the integer lattice and blended smoothly in
+/// between, which gives a continuous value that varies at the scale of one unit with
+/// no visible grid. The lattice is drawn from a seed: the same seed always gives the
+/// same noise, so a texture looks the same from one render to the next.
+///
+/// # Example
+///
+/// ```
+/// use raytracer::{Perlin, Point3};
+///
+/// let perlin = Perlin::new(7);
+/// // Zero on the lattice, smooth in between
+/// assert_eq!(perlin.noise(&Point3::new(1.0, 2.0, 3.0)), 0.0);
+/// assert!(perlin.noise(&Point3::new(1.5, 2.5, 3.5)).abs() <= 1.0);
+/// assert_eq!(perlin.noise(&Point3::new(0.3, 0.1, 0.7)), Perlin::new(7).noise(&Point3::new(0.3, 0.1, 0.7)));
+/// ```
+#[derive(Clone)]
+pub struct Perlin {
+    gradients: Vec<Vec3>,
+    perm_x: Vec<usize>,
+    perm_y: Vec<usize>,
+    perm_z: Vec<usize>,
+}
+
+impl Perlin {
+    /// Creates the noise drawn from `seed`.
+    pub fn new(seed: u64) -> Perlin {
+        let mut rng = SmallRng::seed_from_u64(seed);
+        let gradients = (0..POINT_COUNT)
+            .map(|_| {
+                // Uniform on the unit sphere
+                let z: f64 = rng.random_range(-1.0..1.0);
+                let phi: f64 = rng.random_range(0.0..2.0 * PI);
+                let r = (1.0 - z * z).sqrt();
+                Vec3::new(r * phi.cos(), r * phi.sin(), z)
+            })
+            .collect();
+        let mut permutation = || {
+            let mut perm: Vec<usize> = (0..POINT_COUNT).collect();
+            perm.shuffle(&mut rng);
+            perm
+        };
+        Perlin {
+            perm_x: permutation(),
+            perm_y: permutation(),
+            perm_z: permutation(),
+            gradients,
+        }
+    }
+
+    /// Returns the noise at `p`, roughly between -1 and 1 and 0 at integer coordinates.
+    pub fn noise(&self, p: &Point3) -> f64 {
+        let (i, j, k) = (p.x().floor(), p.y().floor(), p.z().floor());
+        let (u, v, w) = (p.x() - i, p.y() - j, p.z() - k);
+        let (i, j, k) = (i as i64, j as i64, k as i64);
+
+        let mut sum = 0.0;
+        for di in 0..2 {
+            for dj in 0..2 {
+                for dk in 0..2 {
+                    let gradient = self.gradients[self.perm_x[wrap(i + di)]
+                        ^ self.perm_y[wrap(j + dj)]
+                        ^ self.perm_z[wrap(k + dk)]];
+                    let (fi, fj, fk) = (di as f64, dj as f64, dk as f64);
+                    let offset = Vec3::new(u - fi, v - fj, w - fk);
+                    let weight = blend(u, fi) * blend(v, fj) * blend(w, fk);
+                    sum += weight * dot(gradient, offset);
+                }
+            }
+        }
+        sum
+    }
+
+    /// Sums `octaves` layers of noise, each twice as fine and half as strong as the
+    /// previous one. The result keeps the sign of the noise, roughly between -2 and 2.
+    pub fn fbm(&self, p: &Point3, octaves: u32) -> f64 {
+        self.octaves(p, octaves, |n| n)
+    }
+
+    /// Like [`Perlin::fbm`] but sums the absolute value of every layer, which gives
+    /// creased, billowing patterns between 0 and 2, as used for marble and wood.
+    pub fn turbulence(&self, p: &Point3, octaves: u32) -> f64 {
+        self.octaves(p, octaves, f64::abs)
+    }
+
+    fn octaves(&self, p: &Point3, octaves: u32, layer: impl Fn(f64) -> f64) -> f64 {
+        let mut sum = 0.0;
+        let mut p = *p;
+        let mut weight = 1.0;
+        for _ in 0..octaves {
+            sum += weight * layer(self.noise(&p));
+            weight *= 0.5;
+            p = 2.0 * p;
+        }
+        sum
+    }
+}
+
+impl Default for Perlin {
+    /// The noise drawn from the seed 0.
+    fn default() -> Perlin {
+        Perlin::new(0)
+    }
+}
+
+/// Brings a lattice coordinate into the tables, the noise repeating beyond.
+fn wrap(i: i64) -> usize {
+    i.rem_euclid(POINT_COUNT as i64) as usize
+}
+
+/// Weight of the lattice corner at `corner` (0 or 1) for a point at `t` in the cell,
+/// eased so that the noise is smooth across cells.
+fn blend(t: f64, corner: f64) -> f64 {
+    let fade = t * t * t * (t * (t * 6.0 - 15.0) + 10.0);
+    corner * fade + (1.0 - corner) * (1.0 - fade)
+}
+
+/// Colors picked along a value from 0 to 1, blending linearly between stops.
+///
+/// # Example
+///
+/// ```
+/// use raytracer::{Color, ColorRamp};
+///
+/// let ramp = ColorRamp::from_colors(&[Color::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0)]);
+/// assert_eq!(ramp.at(0.25).x(), 0.25);
+/// assert_eq!(ramp.at(2.0).x(), 1.0);
+/// ```
+#[derive(Debug, Clone)]
+pub struct ColorRamp {
+    /// Positions and colors, by increasing position.
+    stops: Vec<(f64, Color)>,
+}
+
+impl ColorRamp {
+    /// Creates a ramp going through `color` at every `(position, color)` stop.
+    ///
+    /// Values before the first stop take its color, values after the last one take
+    /// the color of the last.
+    ///
+    /// # Panics
+    /// If `stops` is empty.
+    pub fn new(mut stops: Vec<(f64, Color)>) -> ColorRamp {
+        assert!(!stops.is_empty(), "a color ramp needs at least one color");
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        ColorRamp { stops }
+    }
+
+    /// Creates a ramp going through `colors` evenly spaced from 0 to 1.
+    ///
+    /// # Panics
+    /// If `colors` is empty.
+    pub fn from_colors(colors: &[Color]) -> ColorRamp {
+        let last = colors.len().saturating_sub(1).max(1) as f64;
+        ColorRamp::new(
+            colors
+                .iter()
+                .enumerate()
+                .map(|(i, &color)| (i as f64 / last, color))
+                .collect(),
+        )
+    }
+
+    /// Returns the color of the ramp at `t`.
+    pub fn at(&self, t: f64) -> Color {
+        let next = self.stops.partition_point(|&(position, _)| position <= t);
+        if next == 0 {
+            return self.stops[0].1;
+        }
+        if next == self.stops.len() {
+            return self.stops[next - 1].1;
+        }
+        let (p0, c0) = self.stops[next - 1];
+        let (p1, c1) = self.stops[next];
+        let s = (t - p0) / (p1 - p0);
+        (1.0 - s) * c0 + s * c1
+    }
+}
+
+/// A cloudy texture: Perlin noise in space mapped onto a color ramp.
+///
+/// The noise varies over `1 / scale` units and is made of `octaves` layers of finer
+/// and finer detail, see [`Perlin::fbm`]. By default it goes from black to white.
+pub struct NoiseTexture {
+    perlin: Perlin,
+    scale: f64,
+    octaves: u32,
+    ramp: ColorRamp,
+}
+
+impl NoiseTexture {
+    /// Creates a noise texture of features about `1 / scale` units wide, with 4 octaves.
+    pub fn new(scale: f64) -> NoiseTexture {
+        NoiseTexture {
+            perlin: Perlin::default(),
+            scale,
+            octaves: 4,
+            ramp: ColorRamp::from_colors(&[Color::new(0.0, 0.0, 0.0), Color::new(1.0, 1.0, 1.0)]),
+        }
+    }
+
+    /// Sets the number of layers of detail, 1 for plain noise.
+    pub fn octaves(mut self, octaves: u32) -> Self {
+        self.octaves = octaves;
+        self
+    }
+
+    /// Sets the colors, from the lowest to the highest noise.
+    pub fn ramp(mut self, ramp: ColorRamp) -> Self {
+        self.ramp = ramp;
+        self
+    }
+
+    /// Draws the noise from `seed`, for a different pattern.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.perlin = Perlin::new(seed);
+        self
+    }
+}
+
+impl Texture for NoiseTexture {
+    fn get_color(&self, _u: f64, _v: f64, point: &Point3) -> Color {
+        let noise = self.perlin.fbm(&(self.scale * *point), self.octaves);
+        self.ramp.at(0.5 * (1.0 + noise))
+    }
+}
+
+/// Marble: thin veins across the X axis, bent by turbulence.
+///
+/// Veins are `1 / scale` units apart. The `distortion` sets how far the turbulence
+/// bends them, in fractions of that spacing: 0 gives straight stripes. By default
+/// the stone is white with dark grey veins.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use raytracer::{Color, ColorRamp, Lambertian, MarbleTexture};
+///
+/// let green = MarbleTexture::new(4.0).distortion(3.0).ramp(ColorRamp::from_colors(&[
+///     Color::new(0.02, 0.1, 0.05),
+///     Color::new(0.3, 0.6, 0.4),
+///     Color::new(0.8, 0.9, 0.85),
+/// ]));
+/// let material = Lambertian::from_texture(Arc::new(green));
+/// ```
+pub struct MarbleTexture {
+    perlin: Perlin,
+    scale: f64,
+    octaves: u32,
+    distortion: f64,
+    ramp: ColorRamp,
+}
+
+impl MarbleTexture {
+    /// Creates a marble of veins `1 / scale` units apart, with 7 octaves of
+    /// turbulence and a distortion of 2.
+    pub fn new(scale: f64) -> MarbleTexture {
+        MarbleTexture {
+            perlin: Perlin::default(),
+            scale,
+            octaves: 7,
+            distortion: 2.0,
+            ramp: ColorRamp::new(vec![
+                (0.0, Color::new(0.15, 0.15, 0.17)),
+                (0.4, Color::new(0.55, 0.55, 0.55)),
+                (1.0, Color::new(0.9, 0.9, 0.88)),
+            ]),
+        }
+    }
+
+    /// Sets the number of layers of detail of the turbulence.
+    pub fn octaves(mut self, octaves: u32) -> Self {
+        self.octaves = octaves;
+        self
+    }
+
+    /// Sets how much the turbulence bends the veins, in fractions of their spacing.
+    pub fn distortion(mut self, distortion: f64) -> Self {
+        self.distortion = distortion;
+        self
+    }
+
+    /// Sets the colors, from the heart of the veins to the stone between them.
+    pub fn ramp(mut self, ramp: ColorRamp) -> Self {
+        self.ramp = ramp;
+        self
+    }
+
+    /// Draws the turbulence from `seed`, for a different pattern.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.perlin = Perlin::new(seed);
+        self
+    }
+}
+
+impl Texture for MarbleTexture {
+    fn get_color(&self, _u: f64, _v: f64, point: &Point3) -> Color {
+        let p = self.scale * *point;
+        let phase = p.x() + self.distortion * self.perlin.turbulence(&p, self.octaves);
+        // Near 0 only close to the veins, where the sine changes sign
+        self.ramp.at((PI * phase).sin().abs())
+    }
+}
+
+/// Stretch of the grain of wood along its trunk, which is longer than it is wide.
+const GRAIN_STRETCH: f64 = 0.25;
+
+/// Wood: growth rings around the Y axis, made irregular by turbulence.
+///
+/// Rings are `1 / scale` units apart. The `distortion` sets how much the turbulence
+/// moves them, in fractions of a ring. The ramp gives the color across a ring, from
+/// the light early wood to the dark late wood.
+pub struct WoodTexture {
+    perlin: Perlin,
+    scale: f64,
+    octaves: u32,
+    distortion: f64,
+    ramp: ColorRamp,
+}
+
+impl WoodTexture {
+    /// Creates a wood of rings `1 / scale` units apart, with 4 octaves of turbulence
+    /// and a distortion of 1.
+    pub fn new(scale: f64) -> WoodTexture {
+        WoodTexture {
+            perlin: Perlin::default(),
+            scale,
+            octaves: 4,
+            distortion: 1.0,
+            ramp: ColorRamp::new(vec![
+                (0.0, Color::new(0.6, 0.38, 0.18)),
+                (0.7, Color::new(0.45, 0.27, 0.12)),
+                (1.0, Color::new(0.25, 0.13, 0.05)),
+            ]),
+        }
+    }
+
+    /// Sets the number of layers of detail of the turbulence.
+    pub fn octaves(mut self, octaves: u32) -> Self {
+        self.octaves = octaves;
+        self
+    }
+
+    /// Sets how much the turbulence moves the rings, in fractions of a ring.
+    pub fn distortion(mut self, distortion: f64) -> Self {
+        self.distortion = distortion;
+        self
+    }
+
+    /// Sets the colors across a ring, from its inner to its outer edge.
+    pub fn ramp(mut self, ramp: ColorRamp) -> Self {
+        self.ramp = ramp;
+        self
+    }
+
+    /// Draws the turbulence from `seed`, for a different pattern.
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.perlin = Perlin::new(seed);
+        self
+    }
+}
+
+impl Texture for WoodTexture {
+    fn get_color(&self, _u: f64, _v: f64, point: &Point3) -> Color {
+        let p = self.scale * *point;
+        let grain = Point3::new(p.x(), GRAIN_STRETCH * p.y(), p.z());
+        let radius = p.x().hypot(p.z()) + self.distortion * self.perlin.turbulence(&grain, self.octaves);
+        self.ramp.at(radius.fract())
+    }
+}
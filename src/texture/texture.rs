@@ -1,5 +1,21 @@
+use std::sync::Arc;
+
 use crate::{Color,Point3};
 
+/// A color varying over surfaces, looked up by materials at each hit point.
+///
+/// Textures are given the texture coordinates `u` and `v` of the point on the
+/// surface, which run from 0 to 1 across most primitives, and the point itself
+/// for patterns defined in space.
 pub trait Texture: Send + Sync {
+    /// Returns the color of the texture at the surface coordinates `(u, v)`, found at `point`.
     fn get_color(&self, u: f64, v:f64, point: &Point3) -> Color;
-}
\ No newline at end of file
+}
+
+/// A shared texture can be used like the texture itself, for example as a part
+/// of a [`CheckerTexture`](crate::CheckerTexture).
+impl<T: Texture + ?Sized> Texture for Arc<T> {
+    fn get_color(&self, u: f64, v: f64, point: &Point3) -> Color {
+        (**self).get_color(u, v, point)
+    }
+}
@@ -0,0 +1,136 @@
+use crate::color::Color;
+
+/// How radiance above 1 is brought into the range a display can show.
+///
+/// Every operator maps each component on its own and leaves dark colors nearly
+/// unchanged; they differ in how they roll off the highlights.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ToneMap {
+    /// Components above 1 are cut to 1: bright areas clip to flat colors.
+    #[default]
+    Clamp,
+    /// `x / (1 + x)`, which never reaches white and flattens the contrast.
+    Reinhard,
+    /// Reinhard with a white point: `white` and anything brighter become white.
+    ///
+    /// A `white` that is not a positive number has no meaning, it gives plain Reinhard.
+    ReinhardExtended { white: f64 },
+    /// The filmic curve of John Hable, made for Uncharted 2, with a toe in the shadows.
+    Hable,
+    /// Krzysztof Narkowicz's fit of the ACES filmic curve: contrasty, with saturated
+    /// highlights that still roll off to white.
+    Aces,
+}
+
+impl ToneMap {
+    /// Maps the linear component `x` into `[0, 1]`.
+    ///
+    /// ```
+    /// use raytracer::ToneMap;
+    ///
+    /// assert_eq!(ToneMap::ReinhardExtended { white: 4.0 }.map(4.0), 1.0);
+    /// // Without a valid white point, black stays black
+    /// assert_eq!(ToneMap::ReinhardExtended { white: 0.0 }.map(0.0), 0.0);
+    /// ```
+    pub fn map(&self, x: f64) -> f64 {
+        let x = x.max(0.0);
+        let mapped = match *self {
+            ToneMap::Clamp => x,
+            ToneMap::Reinhard => x / (1.0 + x),
+            ToneMap::ReinhardExtended { white } if white > 0.0 && white.is_finite() => {
+                x * (1.0 + x / (white * white)) / (1.0 + x)
+            }
+            ToneMap::ReinhardExtended { .. } => x / (1.0 + x),
+            ToneMap::Hable => {
+                // The curve is scaled so that its white point, 11.2, maps to 1
+                const EXPOSURE_BIAS: f64 = 2.0;
+                hable(EXPOSURE_BIAS * x) / hable(11.2)
+            }
+            ToneMap::Aces => {
+                // The fit is for a brighter exposure than the other operators
+                let x = 0.6 * x;
+                x * (2.51 * x + 0.03) / (x * (2.43 * x + 0.59) + 0.14)
+            }
+        };
+        mapped.min(1.0)
+    }
+}
+
+/// The Uncharted 2 curve, before its normalization.
+fn hable(x: f64) -> f64 {
+    const A: f64 = 0.15; // Shoulder strength
+    const B: f64 = 0.50; // Linear strength
+    const C: f64 = 0.10; // Linear angle
+    const D: f64 = 0.20; // Toe strength
+    const E: f64 = 0.02; // Toe numerator
+    const F: f64 = 0.30; // Toe denominator
+    (x * (A * x + C * B) + D * E) / (x * (A * x + B) + D * F) - E / F
+}
+
+/// Turns the linear radiance of a render into colors for a display: exposure,
+/// tone mapping, then the sRGB transfer function.
+///
+/// # Example
+///
+/// ```
+/// use raytracer::{Color, DisplayTransform, ToneMap};
+///
+/// let display = DisplayTransform::new().exposure(1.0).tone_map(ToneMap::Aces);
+/// // One stop brighter, the highlight rolls off instead of clipping
+/// let [r, g, b] = display.to_rgb8(Color::new(4.0, 1.0, 0.1));
+/// assert!(r > g && g > b && r < 255);
+///
+/// // The default one only clamps
+/// assert_eq!(DisplayTransform::new().to_rgb8(Color::new(0.0, 1.0, 20.0)), [0, 255, 255]);
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct DisplayTransform {
+    /// Exposure in stops: each one doubles the radiance before tone mapping.
+    pub exposure: f64,
+    /// How the exposed radiance is brought into `[0, 1]`.
+    pub tone_map: ToneMap,
+}
+
+impl DisplayTransform {
+    /// Creates the default transform: no exposure change and clamping.
+    pub fn new() -> DisplayTransform {
+        Default::default()
+    }
+
+    /// Sets the exposure, in stops.
+    pub fn exposure(mut self, exposure: f64) -> Self {
+        self.exposure = exposure;
+        self
+    }
+
+    /// Sets the tone mapping operator.
+    pub fn tone_map(mut self, tone_map: ToneMap) -> Self {
+        self.tone_map = tone_map;
+        self
+    }
+
+    /// Exposes and tone maps `color`, giving linear components in `[0, 1]`.
+    pub fn apply(&self, color: Color) -> Color {
+        let scale = self.exposure.exp2();
+        let map = |x: f64| self.tone_map.map(scale * x);
+        Color::new(map(color.x()), map(color.y()), map(color.z()))
+    }
+
+    /// Converts `color` into 8-bit sRGB components.
+    pub fn to_rgb8(&self, color: Color) -> [u8; 3] {
+        let display = self.apply(color);
+        let encode = |x: f64| (255.0 * srgb_oetf(x) + 0.5) as u8;
+        [encode(display.x()), encode(display.y()), encode(display.z())]
+    }
+}
+
+/// The sRGB transfer function: encodes a linear component in `[0, 1]` for display.
+///
+/// It is linear near black and close to a gamma of 2.2 above.
+fn srgb_oetf(x: f64) -> f64 {
+    if x <= 0.003_130_8 {
+        12.92 * x
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
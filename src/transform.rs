@@ -0,0 +1,260 @@
+use crate::aabb::Aabb;
+use crate::common;
+use crate::hittable::{HitRecord, Hittable};
+use crate::mat4::Mat4;
+use crate::ray::Ray;
+use crate::vec3::{self, Point3, Vec3};
+
+/// An affine transformation, stored along with its inverse.
+///
+/// Transforms are built from translations, rotations and scalings chained with
+/// [`Transform::then`], in the order they are applied.
+///
+/// # Example
+///
+/// ```
+/// use raytracer::{Point3, Transform, Vec3};
+///
+/// // Spin around the vertical axis, then move up
+/// let t = Transform::rotate_y(90.0).then(&Transform::translate(Vec3::new(0.0, 2.0, 0.0)));
+/// let p = t.point(Point3::new(1.0, 0.0, 0.0));
+/// assert!((p - Point3::new(0.0, 2.0, -1.0)).length() < 1e-9);
+/// assert!((t.inverse().point(p) - Point3::new(1.0, 0.0, 0.0)).length() < 1e-9);
+/// ```
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Transform {
+    matrix: Mat4,
+    inverse: Mat4,
+}
+
+impl Transform {
+    /// The transform leaving everything in place.
+    pub fn identity() -> Transform {
+        Transform::default()
+    }
+
+    /// Creates the transform applying `matrix`, or `None` if it cannot be inverted.
+    pub fn from_matrix(matrix: Mat4) -> Option<Transform> {
+        let inverse = matrix.inverse()?;
+        Some(Transform { matrix, inverse })
+    }
+
+    /// Moves everything by `offset`.
+    pub fn translate(offset: Vec3) -> Transform {
+        let (x, y, z) = (offset.x(), offset.y(), offset.z());
+        Transform {
+            matrix: Mat4::new([
+                [1.0, 0.0, 0.0, x],
+                [0.0, 1.0, 0.0, y],
+                [0.0, 0.0, 1.0, z],
+                [0.0, 0.0, 0.0, 1.0],
+            ]),
+            inverse: Mat4::new([
+                [1.0, 0.0, 0.0, -x],
+                [0.0, 1.0, 0.0, -y],
+                [0.0, 0.0, 1.0, -z],
+                [0.0, 0.0, 0.0, 1.0],
+            ]),
+        }
+    }
+
+    /// Stretches everything away from the origin by a factor along each axis.
+    ///
+    /// A negative factor mirrors along its axis.
+    ///
+    /// # Panics
+    /// If a factor is 0, which would flatten everything.
+    pub fn scale(factors: Vec3) -> Transform {
+        let (x, y, z) = (factors.x(), factors.y(), factors.z());
+        assert!(x != 0.0 && y != 0.0 && z != 0.0, "scale factors must not be 0");
+        Transform {
+            matrix: Mat4::new([
+                [x, 0.0, 0.0, 0.0],
+                [0.0, y, 0.0, 0.0],
+                [0.0, 0.0, z, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ]),
+            inverse: Mat4::new([
+                [1.0 / x, 0.0, 0.0, 0.0],
+                [0.0, 1.0 / y, 0.0, 0.0],
+                [0.0, 0.0, 1.0 / z, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ]),
+        }
+    }
+
+    /// Scales everything by the same factor along every axis.
+    pub fn uniform_scale(factor: f64) -> Transform {
+        Transform::scale(Vec3::new(factor, factor, factor))
+    }
+
+    /// Rotates everything by `degrees` around `axis`, going through the origin.
+    ///
+    /// The rotation is counter-clockwise when looking down the axis towards the origin.
+    pub fn rotate(axis: Vec3, degrees: f64) -> Transform {
+        // Rodrigues' rotation formula, written as a matrix
+        let a = vec3::unit_vector(axis);
+        let (x, y, z) = (a.x(), a.y(), a.z());
+        let theta = common::degrees_to_radians(degrees);
+        let (sin, cos) = theta.sin_cos();
+        let k = 1.0 - cos;
+        let matrix = Mat4::new([
+            [cos + x * x * k, x * y * k - z * sin, x * z * k + y * sin, 0.0],
+            [y * x * k + z * sin, cos + y * y * k, y * z * k - x * sin, 0.0],
+            [z * x * k - y * sin, z * y * k + x * sin, cos + z * z * k, 0.0],
+            [0.0, 0.0, 0.0, 1.0],
+        ]);
+        // The inverse of a rotation is its transpose
+        Transform {
+            matrix,
+            inverse: matrix.transpose(),
+        }
+    }
+
+    /// Rotates everything by `degrees` around the X axis.
+    pub fn rotate_x(degrees: f64) -> Transform {
+        Transform::rotate(Vec3::new(1.0, 0.0, 0.0), degrees)
+    }
+
+    /// Rotates everything by `degrees` around the Y axis.
+    pub fn rotate_y(degrees: f64) -> Transform {
+        Transform::rotate(Vec3::new(0.0, 1.0, 0.0), degrees)
+    }
+
+    /// Rotates everything by `degrees` around the Z axis.
+    pub fn rotate_z(degrees: f64) -> Transform {
+        Transform::rotate(Vec3::new(0.0, 0.0, 1.0), degrees)
+    }
+
+    /// The transform applying this one, then `next`.
+    pub fn then(&self, next: &Transform) -> Transform {
+        Transform {
+            matrix: next.matrix * self.matrix,
+            inverse: self.inverse * next.inverse,
+        }
+    }
+
+    /// The transform undoing this one.
+    pub fn inverse(&self) -> Transform {
+        Transform {
+            matrix: self.inverse,
+            inverse: self.matrix,
+        }
+    }
+
+    /// The matrix of the transform.
+    pub fn matrix(&self) -> &Mat4 {
+        &self.matrix
+    }
+
+    /// Transforms a point.
+    pub fn point(&self, p: Point3) -> Point3 {
+        self.matrix.transform_point(p)
+    }
+
+    /// Transforms a vector, such as a direction or an offset between two points.
+    pub fn vector(&self, v: Vec3) -> Vec3 {
+        self.matrix.transform_vector(v)
+    }
+
+    /// Transforms a normal, so that it stays perpendicular to the transformed surface.
+    ///
+    /// Normals go through the transpose of the inverse matrix rather than the matrix
+    /// itself, which would tilt them on a non-uniform scaling. The result is not of unit length.
+    pub fn normal(&self, n: Vec3) -> Vec3 {
+        self.inverse.transpose().transform_vector(n)
+    }
+
+    /// Transforms a ray. The distances along the ray are unchanged, as its
+    /// direction is not normalized.
+    pub fn ray(&self, r: &Ray) -> Ray {
+        Ray::new(self.point(r.origin()), self.vector(r.direction()))
+    }
+
+    /// Finds where `ray` hits `object` once the object is placed by this transform.
+    ///
+    /// Rather than transforming the object, the ray is brought into the object's own
+    /// space with the inverse transform, and the hit point and normal found there
+    /// are brought back into the world.
+    pub(crate) fn hit_object(&self, object: &dyn Hittable, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let local_ray = self.inverse().ray(ray);
+        let mut rec = object.hit(&local_ray, t_min, t_max)?;
+
+        // The normal still faces against the ray: the transforms of normals and
+        // directions keep the sign of their dot product
+        rec.p = self.point(rec.p);
+        rec.normal = vec3::unit_vector(self.normal(rec.normal));
+        Some(rec)
+    }
+
+    /// The smallest box containing the transformed box `bbox`.
+    pub fn bounding_box(&self, bbox: &Aabb) -> Aabb {
+        if bbox.is_empty() {
+            return *bbox;
+        }
+        let (min, max) = (bbox.min(), bbox.max());
+        let corners: Vec<Point3> = (0..8)
+            .map(|i| {
+                self.point(Point3::new(
+                    if i & 1 == 0 { min.x() } else { max.x() },
+                    if i & 2 == 0 { min.y() } else { max.y() },
+                    if i & 4 == 0 { min.z() } else { max.z() },
+                ))
+            })
+            .collect();
+        Aabb::from_points(&corners)
+    }
+}
+
+/// An object moved, rotated or scaled by a [`Transform`].
+///
+/// Rather than transforming the object, rays are brought into the object's own
+/// space with the inverse transform, and the hit point and normal found there
+/// are brought back into the world. To place the same object several times, see
+/// [`Instance`](crate::Instance).
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use raytracer::{Color, Cube, Lambertian, Point3, Transform, Transformed, Vec3};
+///
+/// let material = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3)));
+/// let cube = Cube::from_center(Point3::new(0.0, 0.0, 0.0), 1.0, material);
+/// let tilted = Transformed::new(
+///     Box::new(cube),
+///     Transform::rotate_y(30.0).then(&Transform::translate(Vec3::new(1.0, 0.5, 0.0))),
+/// );
+/// ```
+pub struct Transformed {
+    object: Box<dyn Hittable>,
+    transform: Transform,
+    bbox: Aabb,
+}
+
+impl Transformed {
+    /// Places `object` in the world with `transform`.
+    pub fn new(object: Box<dyn Hittable>, transform: Transform) -> Transformed {
+        let bbox = transform.bounding_box(&object.bounding_box());
+        Transformed {
+            object,
+            transform,
+            bbox,
+        }
+    }
+
+    /// The transform applied to the object.
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+}
+
+impl Hittable for Transformed {
+    fn hit(&self, ray: &Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        self.transform.hit_object(self.object.as_ref(), ray, t_min, t_max)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        self.bbox
+    }
+}
@@ -0,0 +1,176 @@
+use crate::vec3::*;
+use crate::vec2::UV;
+use crate::material::*;
+use std::sync::Arc;
+use crate::hittable::{HitRecord, Hittable};
+use crate::aabb::Aabb;
+
+/// Below this determinant the ray is considered parallel to the triangle.
+const EPSILON: f64 = 1e-12;
+
+/// A triangle in 3D space defined by its three vertices.
+///
+/// Per-vertex normals can be attached for smooth shading, in which case the normal
+/// at a hit point is interpolated from them instead of being the flat face normal.
+/// Per-vertex texture coordinates can be attached the same way.
+///
+/// # Example
+///
+/// ```
+/// use std::sync::Arc;
+/// use raytracer::{Color, Lambertian, Point3, Triangle, Vec3};
+///
+/// let material = Arc::new(Lambertian::new(Color::new(0.8, 0.3, 0.3)));
+/// let triangle = Triangle::new(
+///     Point3::new(0.0, 0.0, 0.0),
+///     Point3::new(1.0, 0.0, 0.0),
+///     Point3::new(0.0, 1.0, 0.0),
+///     material,
+/// )
+/// .with_normals([Vec3::new(0.0, 0.0, 1.0); 3]);
+/// ```
+pub struct Triangle {
+    /// The first vertex, origin of the two edges.
+    v0: Point3,
+    /// Edge from the first to the second vertex.
+    e1: Vec3,
+    /// Edge from the first to the third vertex.
+    e2: Vec3,
+    /// Unit normal of the plane of the triangle, following the winding order.
+    normal: Vec3,
+    /// Optional unit normals at each vertex, for smooth shading.
+    normals: Option<[Vec3; 3]>,
+    /// Optional texture coordinates at each vertex.
+    uvs: Option<[UV; 3]>,
+    /// Shared reference to the material applied to the triangle.
+    mat: Arc<dyn Material>,
+}
+
+impl Triangle {
+    /// Creates a flat-shaded triangle from its three vertices.
+    ///
+    /// The front face is the one from which the vertices are seen counter-clockwise.
+    ///
+    /// # Arguments
+    /// * `v0`, `v1`, `v2` - The vertices of the triangle.
+    /// * `mat` - The material to apply to the triangle.
+    pub fn new(v0: Point3, v1: Point3, v2: Point3, mat: Arc<dyn Material>) -> Self {
+        let e1 = v1 - v0;
+        let e2 = v2 - v0;
+        Triangle {
+            v0,
+            e1,
+            e2,
+            normal: unit_vector(cross(e1, e2)),
+            normals: None,
+            uvs: None,
+            mat,
+        }
+    }
+
+    /// Attaches a normal to each vertex, to shade the triangle smoothly.
+    pub fn with_normals(mut self, normals: [Vec3; 3]) -> Self {
+        self.normals = Some(normals.map(unit_vector));
+        self
+    }
+
+    /// Attaches texture coordinates to each vertex.
+    pub fn with_uvs(mut self, uvs: [UV; 3]) -> Self {
+        self.uvs = Some(uvs);
+        self
+    }
+
+    /// The three vertices of the triangle.
+    pub fn vertices(&self) -> [Point3; 3] {
+        [self.v0, self.v0 + self.e1, self.v0 + self.e2]
+    }
+}
+
+/// Intersects a ray with the triangle `v0`, `v0 + e1`, `v0 + e2` using the
+/// Möller–Trumbore algorithm.
+///
+/// The intersection is solved directly in barycentric coordinates `(b1, b2)`,
+/// the hit point being `v0 + b1 * e1 + b2 * e2`, without computing the plane first.
+/// Returns the distance along the ray and the two barycentric coordinates.
+pub(crate) fn intersect(
+    v0: Point3,
+    e1: Vec3,
+    e2: Vec3,
+    ray: &crate::ray::Ray,
+    t_min: f64,
+    t_max: f64,
+) -> Option<(f64, f64, f64)> {
+    let pvec = cross(ray.direction(), e2);
+    let det = dot(e1, pvec);
+
+    // If the ray is parallel to the triangle's plane
+    if det.abs() < EPSILON {
+        return None;
+    }
+    let inv_det = 1.0 / det;
+
+    let tvec = ray.origin() - v0;
+    let b1 = dot(tvec, pvec) * inv_det;
+    if !(0.0..=1.0).contains(&b1) {
+        return None;
+    }
+
+    let qvec = cross(tvec, e1);
+    let b2 = dot(ray.direction(), qvec) * inv_det;
+    if b2 < 0.0 || b1 + b2 > 1.0 {
+        return None;
+    }
+
+    let t = dot(e2, qvec) * inv_det;
+    if t < t_min || t > t_max {
+        return None;
+    }
+
+    Some((t, b1, b2))
+}
+
+/// Blends the texture coordinates of the vertices of a triangle at the barycentric
+/// coordinates `(b1, b2)` of a point, the weights of the second and third vertices.
+pub(crate) fn interpolate_uv([uv0, uv1, uv2]: [UV; 3], b1: f64, b2: f64) -> UV {
+    let b0 = 1.0 - b1 - b2;
+    UV::new(
+        b0 * uv0.x() + b1 * uv1.x() + b2 * uv2.x(),
+        b0 * uv0.y() + b1 * uv1.y() + b2 * uv2.y(),
+    )
+}
+
+impl Hittable for Triangle {
+    /// Tests whether a given ray hits the triangle, using the Möller–Trumbore algorithm.
+    fn hit(&self, ray: &crate::ray::Ray, t_min: f64, t_max: f64) -> Option<HitRecord> {
+        let (t, b1, b2) = intersect(self.v0, self.e1, self.e2, ray, t_min, t_max)?;
+
+        let mut rec = HitRecord {
+            t,
+            p: ray.at(t),
+            mat: self.mat.clone(),
+            normal: Default::default(),
+            front_face: Default::default(),
+            uv: match self.uvs {
+                Some(uvs) => interpolate_uv(uvs, b1, b2),
+                None => UV::new(b1, b2),
+            },
+        };
+
+        // The face is decided by the geometric normal, the shading normal only bends it
+        rec.set_face_normal(ray, self.normal);
+        if let Some([n0, n1, n2]) = self.normals {
+            let shading_normal = unit_vector((1.0 - b1 - b2) * n0 + b1 * n1 + b2 * n2);
+            rec.normal = if rec.front_face {
+                shading_normal
+            } else {
+                -shading_normal
+            };
+        }
+
+        Some(rec)
+    }
+
+    fn bounding_box(&self) -> Aabb {
+        Aabb::from_points(&self.vertices())
+    }
+}
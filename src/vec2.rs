@@ -1,4 +1,4 @@
-#[derive(Copy, Clone, Default)]
+#[derive(Debug, Copy, Clone, Default)]
 pub struct Vec2 {
     e: [f64; 2]
 }
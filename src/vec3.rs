@@ -1,5 +1,5 @@
 use std::fmt::{Display, Formatter, Result};
-use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub};
+use std::ops::{Add, AddAssign, Div, DivAssign, Index, Mul, MulAssign, Neg, Sub};
  
 use crate::common;
 
@@ -9,6 +9,8 @@ use crate::common;
 /// # Examples
 ///
 /// ```
+/// use raytracer::Vec3;
+///
 /// let v = Vec3::new(1.0, 2.0, 3.0);
 /// println!("{:?}", v);
 /// ```
@@ -114,6 +116,15 @@ impl Neg for Vec3 {
     }
 }
  
+/// Vec3[axis], with 0, 1 and 2 for x, y and z
+impl Index<usize> for Vec3 {
+    type Output = f64;
+ 
+    fn index(&self, axis: usize) -> &f64 {
+        &self.e[axis]
+    }
+}
+ 
 /// Vec3 += Vec3
 impl AddAssign for Vec3 {
     fn add_assign(&mut self, v: Vec3) {
@@ -292,3 +303,20 @@ pub fn refract(uv: Vec3, n: Vec3, etai_over_etat: f64) -> Vec3 {
     let r_out_parallel = -f64::sqrt(f64::abs(1.0 - r_out_perp.length_squared())) * n;
     r_out_perp + r_out_parallel
 }
+
+/// Returns two unit vectors `(u, v)` completing the unit vector `w` into an
+/// orthonormal basis, such that `cross(u, v) = w`.
+///
+/// Uses the branchless construction of Duff et al., which stays accurate for
+/// every direction of `w`.
+///
+/// **For beginners:** This gives a local frame around a direction, for example
+/// to lay out points on a circle perpendicular to a cylinder's axis.
+pub fn orthonormal_basis(w: Vec3) -> (Vec3, Vec3) {
+    let sign = 1.0_f64.copysign(w.z());
+    let a = -1.0 / (sign + w.z());
+    let b = w.x() * w.y() * a;
+    let u = Vec3::new(1.0 + sign * w.x() * w.x() * a, sign * b, -sign * w.x());
+    let v = Vec3::new(b, sign + w.y() * w.y() * a, -w.y());
+    (u, v)
+}